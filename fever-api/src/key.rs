@@ -56,7 +56,7 @@ impl fmt::Debug for Key {
 impl fmt::Display for Key {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         for byte in self.0.iter() {
-            fmt::LowerHex::fmt(byte, f)?;
+            write!(f, "{:02x}", byte)?;
         }
         Ok(())
     }
@@ -77,6 +77,8 @@ mod tests {
     fn test_formatting() {
         let key = Key(SAMPLE_BYTES);
         assert_eq!(key.to_string(), SAMPLE_HEX);
+        // Bytes below 0x10 keep their leading zero
+        assert_eq!(Key([0x0a; 16]).to_string(), "0a".repeat(16));
     }
 
     #[test]
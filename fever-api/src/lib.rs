@@ -3,7 +3,7 @@ mod request;
 mod response;
 
 pub use crate::key::Key;
-pub use crate::request::{Request, RequestType};
+pub use crate::request::{MAX_ITEM_IDS, Request, RequestError, RequestType};
 pub use crate::response::{Response, ResponsePayload, Feed, FeedsGroup, Group, Item};
 
 fn join_ids(ids: &[u32], out: &mut String) {
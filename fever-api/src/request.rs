@@ -1,9 +1,15 @@
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
 
 use chrono::NaiveDateTime;
 
 use crate::{Key, join_ids};
 
+/// The most items that can be requested by id at once, per the Fever API.
+pub const MAX_ITEM_IDS: usize = 50;
+
 #[derive(Debug, PartialEq)]
 pub enum RequestType {
     None,
@@ -22,16 +28,101 @@ pub enum RequestType {
     MarkItemUnsaved(u32),
     MarkFeedRead(u32, NaiveDateTime),
     MarkGroupRead(u32, NaiveDateTime),
+    // TODO: Implement link support
+}
+
+/// Why a Fever API request couldn't be understood.
+#[derive(Debug, PartialEq)]
+pub enum RequestError {
+    /// The query doesn't start with the api parameter.
+    NotApi,
+    /// The query asks for something this API doesn't support.
+    UnknownAction(String),
+    /// The items request has a parameter other than since_id, max_id, or with_ids.
+    UnknownParameter(String),
+    /// A parameter needed by the request is missing.
+    MissingParameter(&'static str),
+    /// A parameter has a value that can't be used.
+    InvalidParameter(&'static str),
+}
+
+impl fmt::Display for RequestError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RequestError::NotApi => f.write_str("not a Fever API request"),
+            RequestError::UnknownAction(action) => write!(f, "unknown action {:?}", action),
+            RequestError::UnknownParameter(param) => write!(f, "unknown parameter {:?}", param),
+            RequestError::MissingParameter(param) => write!(f, "missing parameter {:?}", param),
+            RequestError::InvalidParameter(param) => write!(f, "invalid value for {:?}", param),
+        }
+    }
+}
+
+impl Error for RequestError { }
+
+/// Decodes the percent-encoded bytes of a query string value,
+/// where a + is a space like in a form body.
+fn percent_decode(value: &str) -> Cow<'_, str> {
+    if !value.contains(['%', '+']) {
+        return Cow::Borrowed(value);
+    }
+
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (b'+', _) => {
+                decoded.push(b' ');
+                i += 1;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    Cow::Owned(String::from_utf8_lossy(&decoded).into_owned())
 }
 
 impl RequestType {
-    pub fn parse<'a, I>(mut query_params: I,
+    pub fn parse<'a, I>(query_params: I,
                         body_params: &HashMap<String, String>)
             -> Option<RequestType>
             where I: Iterator<Item=(&'a str, &'a str)> {
+        RequestType::try_parse(query_params, body_params).ok()
+    }
+
+    /// Parses a request from a raw query string like "api&items&since_id=10",
+    /// for frameworks that don't split the query into parameters.
+    /// Marking items requires body parameters, so isn't parsed here.
+    pub fn parse_query(query: &str) -> Result<RequestType, RequestError> {
+        let query = query.strip_prefix('?').unwrap_or(query);
+        let params: Vec<_> = query.split('&')
+            .filter(|param| !param.is_empty())
+            .map(|param| {
+                let (key, value) = param.split_once('=').unwrap_or((param, ""));
+                (key, percent_decode(value))
+            })
+            .collect();
+        let query_params = params.iter().map(|(key, value)| (*key, &**value));
+        RequestType::try_parse(query_params, &HashMap::new())
+    }
+
+    pub fn try_parse<'a, I>(mut query_params: I,
+                            body_params: &HashMap<String, String>)
+            -> Result<RequestType, RequestError>
+            where I: Iterator<Item=(&'a str, &'a str)> {
         match query_params.next() {
             Some(("api", "")) => (),
-            _ => return None,
+            _ => return Err(RequestError::NotApi),
         };
 
         let action = query_params.next().map(|(k, _)| k);
@@ -39,51 +130,66 @@ impl RequestType {
             None => {
                 let mark = body_params.get("mark").map(|v| &**v);
                 let mark_as = body_params.get("as").map(|v| &**v);
-                let id = body_params.get("id").and_then(|v| v.parse().ok());
-                let before = body_params.get("before")
-                    .and_then(|v| v.parse().ok())
-                    .and_then(|t| NaiveDateTime::from_timestamp_opt(t, 0));
+                let id = || {
+                    body_params.get("id")
+                        .ok_or(RequestError::MissingParameter("id"))?
+                        .parse()
+                        .map_err(|_| RequestError::InvalidParameter("id"))
+                };
+                let before = || {
+                    body_params.get("before")
+                        .ok_or(RequestError::MissingParameter("before"))?
+                        .parse()
+                        .ok()
+                        .and_then(|t| NaiveDateTime::from_timestamp_opt(t, 0))
+                        .ok_or(RequestError::InvalidParameter("before"))
+                };
                 match (mark, mark_as) {
-                    (None, None) => Some(RequestType::None),
+                    (None, None) => Ok(RequestType::None),
                     (Some("item"), Some("read")) =>
-                        id.map(RequestType::MarkItemRead),
+                        id().map(RequestType::MarkItemRead),
                     (Some("item"), Some("unread")) =>
-                        id.map(RequestType::MarkItemUnread),
+                        id().map(RequestType::MarkItemUnread),
                     (Some("item"), Some("saved")) =>
-                        id.map(RequestType::MarkItemSaved),
+                        id().map(RequestType::MarkItemSaved),
                     (Some("item"), Some("unsaved")) =>
-                        id.map(RequestType::MarkItemUnsaved),
+                        id().map(RequestType::MarkItemUnsaved),
                     (Some("feed"), Some("read")) =>
-                        id.and_then(|i| before.map(|b| (i, b)))
-                          .map(|(i, b)| RequestType::MarkFeedRead(i, b)),
+                        Ok(RequestType::MarkFeedRead(id()?, before()?)),
                     (Some("group"), Some("read")) =>
-                        id.and_then(|i| before.map(|b| (i, b)))
-                          .map(|(i, b)| RequestType::MarkGroupRead(i, b)),
-                    _ => None,
+                        Ok(RequestType::MarkGroupRead(id()?, before()?)),
+                    (Some(_), None) => Err(RequestError::MissingParameter("as")),
+                    (None, Some(_)) => Err(RequestError::MissingParameter("mark")),
+                    (Some("item"), Some(_)) | (Some("feed"), Some(_)) |
+                    (Some("group"), Some(_)) => Err(RequestError::InvalidParameter("as")),
+                    (Some(_), Some(_)) => Err(RequestError::InvalidParameter("mark")),
                 }
             },
-            Some("groups") => Some(RequestType::Groups),
-            Some("feeds") => Some(RequestType::Feeds),
-            Some("favicons") => Some(RequestType::Favicons),
+            Some("groups") => Ok(RequestType::Groups),
+            Some("feeds") => Ok(RequestType::Feeds),
+            Some("favicons") => Ok(RequestType::Favicons),
             Some("items") => match query_params.next() {
-                Some(("since_id", val)) =>
-                    val.parse().ok().map(|v| RequestType::ItemsSince(v)),
-                Some(("max_id", val)) =>
-                    val.parse().ok().map(|v| RequestType::ItemsBefore(v)),
+                Some(("since_id", val)) => val.parse()
+                    .map(RequestType::ItemsSince)
+                    .map_err(|_| RequestError::InvalidParameter("since_id")),
+                Some(("max_id", val)) => val.parse()
+                    .map(RequestType::ItemsBefore)
+                    .map_err(|_| RequestError::InvalidParameter("max_id")),
                 Some(("with_ids", val)) => {
-                    let ids: Result<_, _> = val.split(',').map(|v| v.parse()).collect();
-                    ids.map(|v| RequestType::Items(v)).ok()
+                    let ids: Result<Vec<_>, _> = val.split(',')
+                        .map(|v| v.trim().parse())
+                        .collect();
+                    ids.ok()
+                        .filter(|ids| ids.len() <= MAX_ITEM_IDS)
+                        .map(RequestType::Items)
+                        .ok_or(RequestError::InvalidParameter("with_ids"))
                 },
-                None => Some(RequestType::LatestItems),
-                _ => None,
-            },
-            Some("links") => {
-                // TODO: Implement link support
-                None
+                None => Ok(RequestType::LatestItems),
+                Some((param, _)) => Err(RequestError::UnknownParameter(param.to_owned())),
             },
-            Some("unread_item_ids") => Some(RequestType::UnreadItems),
-            Some("saved_item_ids") => Some(RequestType::SavedItems),
-            _ => None,
+            Some("unread_item_ids") => Ok(RequestType::UnreadItems),
+            Some("saved_item_ids") => Ok(RequestType::SavedItems),
+            Some(action) => Err(RequestError::UnknownAction(action.to_owned())),
         }
     }
 
@@ -142,10 +248,12 @@ impl Request {
 
 #[cfg(test)]
 mod tests {
-    use super::RequestType;
+    use std::collections::HashMap;
+
+    use super::{MAX_ITEM_IDS, RequestError, RequestType, percent_decode};
 
     fn parse_query(query: &str) -> Option<RequestType> {
-        None
+        RequestType::parse_query(query).ok()
     }
 
     #[test]
@@ -159,4 +267,69 @@ mod tests {
         assert_eq!(parse_query("api&items&with_ids=0,1,2"),
                    Some(RequestType::Items(vec![0, 1, 2])));
     }
+
+    #[test]
+    fn test_items_with_ids() {
+        let body_params = HashMap::new();
+        let parse_ids = |ids: &str| {
+            let query_params = vec![("api", ""), ("items", ""), ("with_ids", ids)];
+            RequestType::parse(query_params.into_iter(), &body_params)
+        };
+
+        assert_eq!(parse_ids("3,1,4"), Some(RequestType::Items(vec![3, 1, 4])));
+        assert_eq!(parse_ids("3,x"), None);
+
+        assert_eq!(RequestType::parse_query("api&items&with_ids=1,2,3"),
+                   Ok(RequestType::Items(vec![1, 2, 3])));
+        assert_eq!(RequestType::Items(vec![1, 2, 3]).query(), "api&items&with_ids=1,2,3");
+
+        let too_many: Vec<_> = (0..=MAX_ITEM_IDS).map(|i| i.to_string()).collect();
+        assert_eq!(parse_ids(&too_many.join(",")), None);
+    }
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(percent_decode("3%2C1"), "3,1");
+        assert_eq!(percent_decode("Tech+News%2B"), "Tech News+");
+        assert_eq!(percent_decode("100%"), "100%");
+    }
+
+    #[test]
+    fn test_parse_query_errors() {
+        assert_eq!(RequestType::parse_query("?api&items&max_id=250"),
+                   Ok(RequestType::ItemsBefore(250)));
+        assert_eq!(RequestType::parse_query("api=&items&with_ids=3%2C1%2C4"),
+                   Ok(RequestType::Items(vec![3, 1, 4])));
+        assert_eq!(RequestType::parse_query("api&saved_item_ids"),
+                   Ok(RequestType::SavedItems));
+
+        assert_eq!(RequestType::parse_query("feeds"), Err(RequestError::NotApi));
+        assert_eq!(RequestType::parse_query("api&links"),
+                   Err(RequestError::UnknownAction("links".to_owned())));
+        assert_eq!(RequestType::parse_query("api&items&since=3"),
+                   Err(RequestError::UnknownParameter("since".to_owned())));
+        assert_eq!(RequestType::parse_query("api&items&since_id=x"),
+                   Err(RequestError::InvalidParameter("since_id")));
+    }
+
+    #[test]
+    fn test_mark_errors() {
+        let parse_body = |params: &[(&str, &str)]| {
+            let body_params: HashMap<_, _> = params.iter()
+                .map(|&(k, v)| (k.to_owned(), v.to_owned()))
+                .collect();
+            RequestType::try_parse(vec![("api", "")].into_iter(), &body_params)
+        };
+
+        assert_eq!(parse_body(&[("mark", "item"), ("as", "read"), ("id", "7")]),
+                   Ok(RequestType::MarkItemRead(7)));
+        assert_eq!(parse_body(&[("mark", "item"), ("as", "read")]),
+                   Err(RequestError::MissingParameter("id")));
+        assert_eq!(parse_body(&[("mark", "feed"), ("as", "read"), ("id", "7")]),
+                   Err(RequestError::MissingParameter("before")));
+        assert_eq!(parse_body(&[("mark", "item"), ("as", "starred"), ("id", "7")]),
+                   Err(RequestError::InvalidParameter("as")));
+        assert_eq!(parse_body(&[("mark", "item")]),
+                   Err(RequestError::MissingParameter("as")));
+    }
 }
@@ -37,6 +37,9 @@ fn serialize_ids_as_comma_string<S>(value: &[u32], serializer: S)
 pub struct Group {
     pub id: u32,
     pub title: String,
+    /// Not part of the Fever API, but useful for clients showing unread badges.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unread_count: Option<u32>,
 }
 
 #[derive(Serialize)]
@@ -59,6 +62,9 @@ pub struct Feed {
     #[serde(skip_serializing_if = "Option::is_none",
             serialize_with = "serialize_opt_datetime_as_timestamp")]
     pub last_updated_on_time: Option<NaiveDateTime>,
+    /// Not part of the Fever API, but useful for clients showing unread badges.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unread_count: Option<u32>,
 }
 
 #[derive(Serialize)]
@@ -77,6 +83,18 @@ pub struct Item {
     pub is_read: bool,
     #[serde(serialize_with = "serialize_datetime_as_timestamp")]
     pub created_on_time: NaiveDateTime,
+    /// Not part of the Fever API, but lets clients show when an item was
+    /// last changed while still sorting by when it was created.
+    #[serde(serialize_with = "serialize_opt_datetime_as_timestamp")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_on_time: Option<NaiveDateTime>,
+    /// Not part of the Fever API, but useful for clients filtering by tag.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub categories: Vec<String>,
+    /// Not part of the Fever API, but a short plain text preview of the item
+    /// for clients listing items without rendering their html.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preview: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -116,3 +134,70 @@ pub struct Response {
     #[serde(flatten)]
     pub payload: ResponsePayload,
 }
+
+impl Response {
+    /// The version of the Fever API reported in every response.
+    pub const API_VERSION: u32 = 1;
+
+    /// Creates a response with the given payload, which is only included
+    /// if the request was authenticated.
+    pub fn new(auth: bool, payload: ResponsePayload) -> Self {
+        let payload = if auth { payload } else { ResponsePayload::None {} };
+        Response {
+            api_version: Self::API_VERSION,
+            auth,
+            last_refreshed_on_time: None,
+            payload,
+        }
+    }
+
+    /// Creates a response for a request with the wrong api key.
+    pub fn unauthorized() -> Self {
+        Self::new(false, ResponsePayload::None {})
+    }
+
+    /// Sets when the server last refreshed its feeds.
+    pub fn with_last_refreshed_on_time(self, time: NaiveDateTime) -> Self {
+        Response { last_refreshed_on_time: Some(time), ..self }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use serde_json::{Value, json};
+
+    use super::{Response, ResponsePayload};
+
+    #[test]
+    fn test_response_envelope() {
+        let payloads = vec![
+            ResponsePayload::None {},
+            ResponsePayload::UnreadItems { unread_item_ids: vec![1, 2] },
+            ResponsePayload::Items { items: Vec::new(), total_items: 0 },
+        ];
+        for payload in payloads {
+            let response = Response::new(true, payload);
+            let value = serde_json::to_value(&response).unwrap();
+            assert_eq!(value["api_version"], Response::API_VERSION);
+            assert_eq!(value["auth"], 1);
+            assert!(value.get("last_refreshed_on_time").is_none());
+        }
+
+        let refreshed = NaiveDate::from_ymd_opt(2019, 4, 1).unwrap().and_hms_opt(7, 30, 0).unwrap();
+        let response = Response::new(true, ResponsePayload::SavedItems { saved_item_ids: vec![3] })
+            .with_last_refreshed_on_time(refreshed);
+        let value = serde_json::to_value(&response).unwrap();
+        assert_eq!(value, json!({
+            "api_version": 1,
+            "auth": 1,
+            "last_refreshed_on_time": 1554103800,
+            "saved_item_ids": "3",
+        }));
+
+        let response = Response::new(false, ResponsePayload::UnreadItems { unread_item_ids: vec![1] });
+        let value: Value = serde_json::to_value(&response).unwrap();
+        assert_eq!(value, json!({ "api_version": 1, "auth": 0 }));
+        assert_eq!(value, serde_json::to_value(Response::unauthorized()).unwrap());
+    }
+}
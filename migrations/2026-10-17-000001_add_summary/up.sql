@@ -0,0 +1 @@
+ALTER TABLE item ADD summary TEXT;
@@ -0,0 +1 @@
+ALTER TABLE feed ADD is_disabled BOOLEAN NOT NULL DEFAULT (false);
@@ -0,0 +1,5 @@
+CREATE TABLE feed_body (
+  feed_id INTEGER PRIMARY KEY REFERENCES feed ON DELETE CASCADE,
+  body BYTEA NOT NULL,
+  fetched TIMESTAMP NOT NULL DEFAULT (now() at time zone 'utc')
+);
@@ -0,0 +1,6 @@
+CREATE TABLE item_category (
+  item_id INTEGER NOT NULL REFERENCES item ON DELETE CASCADE,
+  term VARCHAR NOT NULL,
+  scheme VARCHAR,
+  PRIMARY KEY (item_id, term)
+);
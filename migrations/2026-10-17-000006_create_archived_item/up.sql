@@ -0,0 +1,15 @@
+-- Mirrors the columns of item, so saved items can be moved out of it
+CREATE TABLE archived_item (
+  id INTEGER PRIMARY KEY,
+  url VARCHAR,
+  title VARCHAR NOT NULL,
+  content TEXT NOT NULL,
+  published TIMESTAMP NOT NULL,
+  feed_id INTEGER NOT NULL REFERENCES feed,
+  is_read BOOLEAN NOT NULL,
+  is_saved BOOLEAN NOT NULL,
+  author VARCHAR,
+  fetched TIMESTAMP NOT NULL,
+  guid VARCHAR,
+  summary TEXT
+);
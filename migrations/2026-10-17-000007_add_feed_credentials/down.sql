@@ -0,0 +1,3 @@
+ALTER TABLE feed DROP COLUMN auth_token;
+ALTER TABLE feed DROP COLUMN auth_password;
+ALTER TABLE feed DROP COLUMN auth_username;
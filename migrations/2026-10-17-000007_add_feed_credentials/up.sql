@@ -0,0 +1,4 @@
+-- Credentials are stored in plaintext since they are sent with each fetch.
+ALTER TABLE feed ADD auth_username VARCHAR;
+ALTER TABLE feed ADD auth_password VARCHAR;
+ALTER TABLE feed ADD auth_token VARCHAR;
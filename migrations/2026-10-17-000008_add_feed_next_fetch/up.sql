@@ -0,0 +1 @@
+ALTER TABLE feed ADD next_fetch TIMESTAMP;
@@ -0,0 +1 @@
+ALTER TABLE feed ADD read_before TIMESTAMP;
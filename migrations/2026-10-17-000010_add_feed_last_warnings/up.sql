@@ -0,0 +1 @@
+ALTER TABLE feed ADD last_warnings TEXT;
@@ -0,0 +1,11 @@
+CREATE TABLE fetch_log (
+  id SERIAL PRIMARY KEY,
+  feed_id INTEGER NOT NULL REFERENCES feed ON DELETE CASCADE,
+  fetched TIMESTAMP NOT NULL DEFAULT (now() at time zone 'utc'),
+  status VARCHAR NOT NULL,
+  http_code INTEGER,
+  item_count INTEGER NOT NULL DEFAULT 0,
+  error TEXT
+);
+
+CREATE INDEX fetch_log_feed_id ON fetch_log (feed_id, id);
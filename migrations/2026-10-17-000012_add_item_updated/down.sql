@@ -0,0 +1,2 @@
+ALTER TABLE archived_item DROP COLUMN updated;
+ALTER TABLE item DROP COLUMN updated;
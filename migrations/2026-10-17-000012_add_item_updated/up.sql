@@ -0,0 +1,2 @@
+ALTER TABLE item ADD updated TIMESTAMP;
+ALTER TABLE archived_item ADD updated TIMESTAMP;
@@ -0,0 +1 @@
+ALTER TABLE feed DROP COLUMN mark_read_on_fetch;
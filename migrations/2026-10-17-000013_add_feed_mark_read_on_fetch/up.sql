@@ -0,0 +1 @@
+ALTER TABLE feed ADD mark_read_on_fetch BOOLEAN NOT NULL DEFAULT FALSE;
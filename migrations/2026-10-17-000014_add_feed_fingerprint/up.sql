@@ -0,0 +1 @@
+ALTER TABLE feed ADD fingerprint BIGINT;
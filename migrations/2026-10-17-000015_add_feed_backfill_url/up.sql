@@ -0,0 +1 @@
+ALTER TABLE feed ADD backfill_url VARCHAR;
@@ -0,0 +1 @@
+DROP TABLE archived_item_category;
@@ -0,0 +1 @@
+ALTER TABLE feed DROP COLUMN has_custom_title;
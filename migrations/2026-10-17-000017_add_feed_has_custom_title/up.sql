@@ -0,0 +1 @@
+ALTER TABLE feed ADD has_custom_title BOOLEAN NOT NULL DEFAULT (false);
@@ -0,0 +1,36 @@
+WITH numbered_item_stats AS (
+    SELECT
+        id,
+        feed_id,
+        row_number() OVER (PARTITION BY feed_id ORDER BY id DESC) AS feed_num
+    FROM item
+), feed_latest_stats AS (
+    SELECT
+        feed_id,
+        min(id) AS min_id
+    FROM numbered_item_stats
+    WHERE feed_num <= $2
+    GROUP BY feed_id
+), archived AS (
+    DELETE FROM item
+    WHERE
+        is_saved AND
+        published < $1 AND
+        id < (SELECT min_id FROM feed_latest_stats WHERE feed_id=item.feed_id)
+    RETURNING
+        id, url, title, content, published, feed_id, is_read, is_saved,
+        author, fetched, guid, summary, updated
+), archived_categories AS (
+    INSERT INTO archived_item_category (item_id, term, scheme)
+    SELECT item_id, term, scheme
+    FROM item_category
+    WHERE item_id IN (SELECT id FROM archived)
+)
+INSERT INTO archived_item (
+    id, url, title, content, published, feed_id, is_read, is_saved,
+    author, fetched, guid, summary, updated
+)
+SELECT
+    id, url, title, content, published, feed_id, is_read, is_saved,
+    author, fetched, guid, summary, updated
+FROM archived;
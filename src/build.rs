@@ -0,0 +1,132 @@
+use std::time::SystemTime;
+
+use atom_syndication::{Entry, Feed, FixedDateTime, Link};
+use chrono::{DateTime, Utc};
+
+use crate::parse::LinkExt;
+
+fn utc_date(time: SystemTime) -> FixedDateTime {
+    DateTime::<Utc>::from(time).into()
+}
+
+pub trait FeedExt {
+    /// Sets the updated date of this feed to that of its latest entry,
+    /// leaving it unchanged if the feed has no entries.
+    fn touch_updated(&mut self);
+
+    /// Adds a self link to the given url, which is where the feed is served,
+    /// unless the feed already has one.
+    fn ensure_self_link(&mut self, url: &str);
+
+    /// Replaces any self links of this feed with one to the given url.
+    fn set_self_link(&mut self, url: &str);
+}
+
+fn self_link(url: &str) -> Link {
+    Link {
+        href: url.to_owned(),
+        rel: "self".to_owned(),
+        mime_type: Some("application/atom+xml".to_owned()),
+        ..Default::default()
+    }
+}
+
+impl FeedExt for Feed {
+    fn touch_updated(&mut self) {
+        if let Some(updated) = self.entries.iter().map(|entry| entry.updated).max() {
+            self.updated = updated;
+        }
+    }
+
+    fn ensure_self_link(&mut self, url: &str) {
+        if !self.links.iter().any(|link| link.is_self()) {
+            self.links.push(self_link(url));
+        }
+    }
+
+    fn set_self_link(&mut self, url: &str) {
+        self.links.retain(|link| !link.is_self());
+        self.links.push(self_link(url));
+    }
+}
+
+pub trait EntryExt {
+    /// Sets the updated date of this entry from a system time, in UTC.
+    fn set_updated_system_time(&mut self, time: SystemTime);
+
+    /// Sets the published date of this entry from a system time, in UTC.
+    fn set_published_system_time(&mut self, time: SystemTime);
+}
+
+impl EntryExt for Entry {
+    fn set_updated_system_time(&mut self, time: SystemTime) {
+        self.updated = utc_date(time);
+    }
+
+    fn set_published_system_time(&mut self, time: SystemTime) {
+        self.published = Some(utc_date(time));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use atom_syndication::{Entry, Feed, FixedDateTime};
+    use chrono::DateTime;
+    use super::{EntryExt, FeedExt};
+
+    fn date(date: &str) -> FixedDateTime {
+        DateTime::parse_from_rfc3339(date).unwrap()
+    }
+
+    fn entry(updated: &str) -> Entry {
+        Entry { updated: date(updated), ..Default::default() }
+    }
+
+    #[test]
+    fn test_touch_updated() {
+        let mut feed = Feed { updated: date("2019-01-01T00:00:00Z"), ..Default::default() };
+        feed.touch_updated();
+        assert_eq!(feed.updated, date("2019-01-01T00:00:00Z"));
+
+        feed.entries = vec![
+            entry("2019-04-01T07:30:00Z"),
+            entry("2019-04-03T07:30:00+02:00"),
+            entry("2019-04-02T07:30:00Z"),
+        ];
+        feed.touch_updated();
+        assert_eq!(feed.updated, date("2019-04-03T07:30:00+02:00"));
+        assert_eq!(feed.updated.to_rfc3339(), "2019-04-03T07:30:00+02:00");
+    }
+
+    #[test]
+    fn test_self_link() {
+        let self_links = |feed: &Feed| -> Vec<String> {
+            feed.links.iter()
+                .filter(|link| link.rel == "self")
+                .map(|link| link.href.clone())
+                .collect()
+        };
+
+        let mut feed = Feed::default();
+        feed.ensure_self_link("http://techcrunch.com/feed/");
+        feed.ensure_self_link("https://techcrunch.com/feed/");
+        assert_eq!(self_links(&feed), ["http://techcrunch.com/feed/"]);
+        assert_eq!(feed.links[0].mime_type.as_deref(), Some("application/atom+xml"));
+
+        feed.set_self_link("https://techcrunch.com/feed/");
+        assert_eq!(self_links(&feed), ["https://techcrunch.com/feed/"]);
+        assert_eq!(feed.links.len(), 1);
+    }
+
+    #[test]
+    fn test_system_time() {
+        let time = SystemTime::UNIX_EPOCH + Duration::from_secs(1554103800);
+        let mut entry = Entry::default();
+        entry.set_updated_system_time(time);
+        entry.set_published_system_time(time - Duration::from_secs(60 * 60));
+        assert_eq!(entry.updated.to_rfc3339(), "2019-04-01T07:30:00+00:00");
+        assert_eq!(entry.published.unwrap().to_rfc3339(), "2019-04-01T06:30:00+00:00");
+    }
+}
@@ -1,9 +1,20 @@
+use std::process;
+use std::time::Duration;
+
+use chrono::Utc;
 use diesel::r2d2;
 use diesel::Connection;
 use diesel::pg::PgConnection;
+use log::error;
+use serde_json::{Value, json};
 
 use crate::data;
 use crate::fetch;
+use crate::handling;
+use crate::models::feed::Feed;
+use crate::models::fetch_log::FetchLog;
+use crate::models::group::Group;
+use crate::publish;
 use crate::serve;
 
 pub type PgConnectionManager = r2d2::ConnectionManager<PgConnection>;
@@ -12,15 +23,121 @@ pub type PooledPgConnection = r2d2::PooledConnection<PgConnectionManager>;
 
 pub struct Feeds {
     database_url: String,
+    default_group: Option<String>,
+    max_feed_size: usize,
+    pool_size: Option<u32>,
+    ingest_options: fetch::IngestOptions,
+    response_options: handling::ResponseOptions,
+    publish_options: publish::PublishOptions,
 }
 
 impl Feeds {
     pub fn new(database_url: String) -> Self {
-        Feeds { database_url }
+        Feeds {
+            database_url,
+            default_group: None,
+            max_feed_size: fetch::DEFAULT_MAX_FEED_SIZE,
+            pool_size: None,
+            ingest_options: fetch::IngestOptions::default(),
+            response_options: handling::ResponseOptions::default(),
+            publish_options: publish::PublishOptions::default(),
+        }
+    }
+
+    /// Sets the group that new subscriptions are added to.
+    pub fn with_default_group(self, default_group: Option<String>) -> Self {
+        Feeds { default_group, ..self }
+    }
+
+    /// Sets the largest feed, in bytes, that will be downloaded.
+    pub fn with_max_feed_size(self, max_feed_size: usize) -> Self {
+        Feeds { max_feed_size, ..self }
+    }
+
+    /// Sets the most database connections the server will open at once,
+    /// rather than r2d2's default of 10.
+    pub fn with_pool_size(self, pool_size: u32) -> Self {
+        Feeds { pool_size: Some(pool_size), ..self }
+    }
+
+    /// Sets the query parameters removed from entry links when ingesting,
+    /// such as utm_* tracking parameters.
+    pub fn with_strip_params(self, strip_params: Vec<String>) -> Self {
+        let ingest_options = fetch::IngestOptions { strip_params, ..self.ingest_options };
+        Feeds { ingest_options, ..self }
+    }
+
+    /// Sets how many pages of older entries to backfill when subscribing
+    /// to an archived or paged feed.
+    pub fn with_backfill_pages(self, backfill_pages: usize) -> Self {
+        let ingest_options = fetch::IngestOptions { backfill_pages, ..self.ingest_options };
+        Feeds { ingest_options, ..self }
+    }
+
+    /// Sets the least time between fetches of each feed,
+    /// which is also used for feeds that don't say how often they update.
+    pub fn with_fetch_interval(self, fetch_interval: Duration) -> Self {
+        let ingest_options = fetch::IngestOptions { fetch_interval, ..self.ingest_options };
+        Feeds { ingest_options, ..self }
+    }
+
+    /// Sets how many items are inserted per transaction when ingesting.
+    pub fn with_insert_batch_size(self, insert_batch_size: usize) -> Self {
+        let ingest_options = fetch::IngestOptions { insert_batch_size, ..self.ingest_options };
+        Feeds { ingest_options, ..self }
+    }
+
+    /// Sets the most feeds that are fetched at once.
+    pub fn with_fetch_limit(self, fetch_limit: usize) -> Self {
+        let ingest_options = fetch::IngestOptions {
+            fetch_limit: Some(fetch_limit),
+            ..self.ingest_options
+        };
+        Feeds { ingest_options, ..self }
+    }
+
+    /// Sets whether category terms are lowercased when ingesting.
+    pub fn with_fold_category_case(self, fold_category_case: bool) -> Self {
+        let ingest_options = fetch::IngestOptions { fold_category_case, ..self.ingest_options };
+        Feeds { ingest_options, ..self }
+    }
+
+    /// Sets whether the last fetched body of each feed is kept for reparsing.
+    pub fn with_store_bodies(self, store_bodies: bool) -> Self {
+        let ingest_options = fetch::IngestOptions { store_bodies, ..self.ingest_options };
+        Feeds { ingest_options, ..self }
+    }
+
+    /// Sets whether scripts and other unsafe markup are removed from
+    /// item html served to API clients.
+    pub fn with_sanitize_html(self, sanitize_html: bool) -> Self {
+        let response_options = handling::ResponseOptions { sanitize_html };
+        Feeds { response_options, ..self }
+    }
+
+    /// Sets the public url of the server, which self links of served feeds
+    /// are built from instead of the Host header of each request.
+    pub fn with_base_url(self, base_url: Option<String>) -> Self {
+        let publish_options = publish::PublishOptions { base_url, ..self.publish_options };
+        Feeds { publish_options, ..self }
+    }
+
+    /// Sets the WebSub hub that served feeds link to.
+    pub fn with_hub_url(self, hub_url: Option<String>) -> Self {
+        let publish_options = publish::PublishOptions { hub_url, ..self.publish_options };
+        Feeds { publish_options, ..self }
+    }
+
+    fn fetcher(&self) -> fetch::Fetcher {
+        fetch::Fetcher::new(self.max_feed_size)
     }
 
     fn establish_connection_pool(&self) -> PgConnectionPool {
-        PgConnectionPool::new(PgConnectionManager::new(&*self.database_url))
+        let mut builder = PgConnectionPool::builder();
+        if let Some(pool_size) = self.pool_size {
+            builder = builder.max_size(pool_size);
+        }
+        builder.build(PgConnectionManager::new(&*self.database_url))
             .expect("Failed to create pool.")
     }
 
@@ -31,25 +148,393 @@ impl Feeds {
 
     pub async fn serve(self, port: u16, creds: Option<(String, String)>) {
         let pool = self.establish_connection_pool();
-        serve::serve(port, creds, pool).await;
+        let fetcher = self.fetcher();
+        serve::serve(
+            port,
+            creds,
+            fetcher,
+            self.ingest_options,
+            self.response_options,
+            self.publish_options,
+            pool,
+        ).await;
     }
 
-    pub async fn fetch(self) {
+    pub async fn fetch(self, json: bool) {
         let mut conn = self.establish_connection();
-        fetch::fetch_items(&mut conn).await
+        let count = fetch::fetch_items(&self.fetcher(), &self.ingest_options, &mut conn).await
             .expect("Error fetching feeds");
+
+        if json {
+            println!("{}", json!({ "new_items": count }));
+        }
     }
 
-    pub async fn subscribe(self, url: &str) {
+    pub fn reparse(self) {
         let mut conn = self.establish_connection();
-        fetch::subscribe(url, &mut conn).await
-            .expect("Error subscribing to feed");
+        fetch::reparse_items(&self.ingest_options, &mut conn)
+            .expect("Error reparsing feeds");
+    }
+
+    pub async fn subscribe(
+        self,
+        url: &str,
+        credentials: Option<fetch::Credentials<'_>>,
+        title: Option<&str>,
+        json: bool,
+    ) -> fetch::Subscription {
+        let mut conn = self.establish_connection();
+        let group = self.default_group.as_deref();
+        let fetcher = self.fetcher();
+        let result = fetch::subscribe(
+            url, credentials, title, group, &fetcher, &self.ingest_options, &mut conn,
+        ).await;
+
+        match result {
+            Ok(subscription) => {
+                if json {
+                    println!("{}", subscription_json(&subscription));
+                } else {
+                    println!("Subscribed to {} ({}) as feed {}",
+                        subscription.title, subscription.url, subscription.feed_id);
+                }
+                subscription
+            }
+            Err(err) if json => {
+                println!("{}", json!({ "url": url, "error": err.to_string() }));
+                process::exit(1);
+            }
+            Err(err) => panic!("Error subscribing to feed: {}", err),
+        }
+    }
+
+    pub async fn subscribe_all(self, list: &str, json: bool) {
+        let mut conn = self.establish_connection();
+        let group = self.default_group.as_deref();
+        let results = fetch::subscribe_all(list, group, &self.fetcher(), &self.ingest_options, &mut conn).await
+            .expect("Error subscribing to feeds");
+
+        if json {
+            println!("{}", subscribe_all_json(&results));
+        } else {
+            println!("Subscribed to {} feeds, {} failed",
+                results.subscribed.len(), results.failed.len());
+        }
     }
 
-    pub fn prune(self) {
+    pub fn prune(self, vacuum: bool, json: bool) {
         let mut conn = self.establish_connection();
         let count = data::prune_read_items(&mut conn)
             .expect("Error deleting read items");
-        println!("Pruned {} read items", count);
+        if !json {
+            println!("Pruned {} read items", count);
+        }
+
+        let reclaimed = if vacuum {
+            let reclaimed = data::vacuum_items(&mut conn)
+                .expect("Error vacuuming items");
+            if !json {
+                println!("Vacuum reclaimed {} bytes", reclaimed);
+            }
+            Some(reclaimed)
+        } else {
+            None
+        };
+
+        if json {
+            println!("{}", prune_json(count, reclaimed));
+        }
+    }
+
+    pub fn unsubscribe(self, feed: &str, keep_saved: bool) {
+        let mut conn = self.establish_connection();
+        let found = find_feed(feed, &mut conn);
+        let result = if keep_saved {
+            data::delete_feed_keeping_saved(&found, &mut conn)
+        } else {
+            data::delete_feed(&found, &mut conn)
+        };
+        let count = result.expect("Error deleting feed");
+        println!("Unsubscribed from {} ({}), deleting {} items", found.title, found.url, count);
+    }
+
+    /// Continues backfilling a feed's older entries from where it stopped.
+    pub async fn backfill(self, feed: &str) {
+        let mut conn = self.establish_connection();
+        let found = find_feed(feed, &mut conn);
+        if found.backfill_url.is_none() {
+            println!("Nothing left to backfill for {} ({})", found.title, found.url);
+            return;
+        }
+
+        let count = fetch::resume_backfill(
+            &found, &self.fetcher(), &self.ingest_options, &mut conn,
+        ).await.expect("Error backfilling feed");
+        println!("Backfilled {} items of {} ({})", count, found.title, found.url);
+    }
+
+    pub fn mark_read_on_fetch(self, feed: &str, enabled: bool) {
+        let mut conn = self.establish_connection();
+        let found = find_feed(feed, &mut conn);
+        data::set_mark_read_on_fetch(&found, enabled, &mut conn)
+            .expect("Error updating feed");
+        if enabled {
+            println!("New items of {} ({}) will be marked read", found.title, found.url);
+        } else {
+            println!("New items of {} ({}) will be unread", found.title, found.url);
+        }
+    }
+
+    /// Marks every item of a feed, or of each feed in a group, unread again.
+    pub fn mark_unread(self, target: &str, is_group: bool, skip_saved: bool) {
+        let mut conn = self.establish_connection();
+        let (name, feed_ids) = if is_group {
+            let groups = data::load_groups(&mut conn)
+                .expect("Error loading groups");
+            let Some(group) = find_group(target, &groups) else {
+                error!("No group {}", target);
+                process::exit(1);
+            };
+            let feed_ids = data::load_feeds(&mut conn)
+                .expect("Error loading feeds")
+                .into_iter()
+                .filter(|feed| feed.group_id == Some(group.id))
+                .map(|feed| feed.id)
+                .collect();
+            (group.title.clone(), feed_ids)
+        } else {
+            let found = find_feed(target, &mut conn);
+            (found.title, vec![found.id])
+        };
+
+        let count = data::mark_feeds_unread(&feed_ids, skip_saved, &mut conn)
+            .expect("Error marking items unread");
+        println!("Marked {} items of {} unread", count, name);
+    }
+
+    pub fn fetch_log(self, feed: &str, limit: i64, json: bool) {
+        let mut conn = self.establish_connection();
+        let found = find_feed(feed, &mut conn);
+        let log = data::load_fetch_log(&found, limit, &mut conn)
+            .expect("Error loading fetch log");
+
+        if json {
+            let entries: Vec<_> = log.iter().map(fetch_log_json).collect();
+            println!("{}", Value::from(entries));
+        } else {
+            for entry in &log {
+                println!("{}", format_fetch_log(entry));
+            }
+        }
+    }
+
+    pub fn archive(self, days: i64) {
+        let mut conn = self.establish_connection();
+        let before = Utc::now().naive_utc() - chrono::Duration::days(days);
+        let count = data::archive_saved_items(before, &mut conn)
+            .expect("Error archiving saved items");
+        println!("Archived {} saved items", count);
+    }
+}
+
+fn subscription_json(subscription: &fetch::Subscription) -> Value {
+    json!({
+        "url": subscription.url,
+        "title": subscription.title,
+        "feed_id": subscription.feed_id,
+        "items": subscription.item_count,
+        "duplicate_of": subscription.duplicate_of,
+    })
+}
+
+fn subscribe_all_json(results: &fetch::SubscribeAllResults) -> Value {
+    let failed: Vec<_> = results.failed.iter()
+        .map(|(url, err)| json!({ "url": url, "error": err }))
+        .collect();
+    json!({
+        "subscribed": results.subscribed.iter()
+            .map(subscription_json)
+            .collect::<Vec<_>>(),
+        "skipped": results.skipped,
+        "failed": failed,
+    })
+}
+
+/// Interprets a feed given on the command line as an id if it's a number,
+/// otherwise it's treated as a url.
+fn parse_feed_id(feed: &str) -> Option<i32> {
+    feed.trim().parse().ok()
+}
+
+/// Loads a feed given by id or url on the command line,
+/// exiting if there's no such subscription.
+fn find_feed(feed: &str, conn: &mut PgConnection) -> Feed {
+    let found = match parse_feed_id(feed) {
+        Some(id) => data::load_feed(id, conn),
+        None => data::find_feed_by_url(feed, conn),
+    }.expect("Error loading feed");
+    match found {
+        Some(found) => found,
+        None => {
+            error!("No subscription to {}", feed);
+            process::exit(1);
+        }
+    }
+}
+
+/// Finds a group given on the command line by id or title, ignoring case.
+fn find_group<'a>(group: &str, groups: &'a [Group]) -> Option<&'a Group> {
+    let group = group.trim();
+    let id = group.parse().ok();
+    groups.iter().find(|found| Some(found.id) == id)
+        .or_else(|| groups.iter().find(|found| found.title.eq_ignore_ascii_case(group)))
+}
+
+fn format_fetch_log(entry: &FetchLog) -> String {
+    let mut line = format!("{} {}", entry.fetched.format("%Y-%m-%d %H:%M:%S"), entry.status);
+    if let Some(http_code) = entry.http_code {
+        line += &format!(" ({})", http_code);
+    }
+    line += &format!(", {} new items", entry.item_count);
+    if let Some(error) = &entry.error {
+        line += &format!(": {}", error);
+    }
+    line
+}
+
+fn fetch_log_json(entry: &FetchLog) -> Value {
+    json!({
+        "fetched": entry.fetched.and_utc().to_rfc3339(),
+        "status": entry.status,
+        "http_code": entry.http_code,
+        "item_count": entry.item_count,
+        "error": entry.error,
+    })
+}
+
+fn prune_json(pruned: usize, reclaimed_bytes: Option<i64>) -> Value {
+    let mut value = json!({ "pruned": pruned });
+    if let Some(reclaimed_bytes) = reclaimed_bytes {
+        value["reclaimed_bytes"] = reclaimed_bytes.into();
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use chrono::NaiveDate;
+    use serde_json::json;
+
+    use crate::data;
+    use crate::fetch::Subscription;
+    use crate::models::fetch_log::FetchLog;
+    use crate::models::group::Group;
+    use crate::testing;
+    use super::{
+        Feeds,
+        fetch_log_json, find_group, format_fetch_log, parse_feed_id, prune_json,
+        subscription_json,
+    };
+
+    #[test]
+    fn test_connection_pool() {
+        let Some(database_url) = testing::migrated_database_url() else { return };
+        let pool = Feeds::new(database_url).with_pool_size(2).establish_connection_pool();
+
+        // More queries than connections run at once, so some wait their turn
+        let queries: Vec<_> = (0..8)
+            .map(|_| {
+                let pool = pool.clone();
+                thread::spawn(move || {
+                    let mut conn = pool.get().expect("Error getting connection from pool");
+                    data::load_feeds(&mut conn).map(|feeds| feeds.len())
+                })
+            })
+            .collect();
+        for query in queries {
+            assert!(query.join().unwrap().is_ok());
+        }
+        assert!(pool.state().connections <= 2);
+    }
+
+    #[test]
+    fn test_parse_feed_id() {
+        assert_eq!(parse_feed_id("42"), Some(42));
+        assert_eq!(parse_feed_id(" 42 "), Some(42));
+        assert_eq!(parse_feed_id("http://techcrunch.com/feed/"), None);
+        assert_eq!(parse_feed_id("42.xml"), None);
+    }
+
+    #[test]
+    fn test_find_group() {
+        let groups = vec![
+            Group { id: 1, title: "Tech".to_owned() },
+            Group { id: 2, title: "Status pages".to_owned() },
+            Group { id: 3, title: "1".to_owned() },
+        ];
+        let found = |group| find_group(group, &groups).map(|group| group.id);
+        assert_eq!(found("2"), Some(2));
+        assert_eq!(found("status PAGES "), Some(2));
+        // Ids take precedence over titles that look like them
+        assert_eq!(found("1"), Some(1));
+        assert_eq!(found("News"), None);
+    }
+
+    #[test]
+    fn test_format_fetch_log() {
+        let fetched = NaiveDate::from_ymd_opt(2019, 4, 1).unwrap().and_hms_opt(7, 30, 0).unwrap();
+        let mut entry = FetchLog {
+            id: 1,
+            feed_id: 1,
+            fetched,
+            status: "ok".to_owned(),
+            http_code: None,
+            item_count: 3,
+            error: None,
+        };
+        assert_eq!(format_fetch_log(&entry), "2019-04-01 07:30:00 ok, 3 new items");
+
+        entry.status = "gone".to_owned();
+        entry.http_code = Some(410);
+        entry.item_count = 0;
+        entry.error = Some("feed is gone".to_owned());
+        assert_eq!(
+            format_fetch_log(&entry),
+            "2019-04-01 07:30:00 gone (410), 0 new items: feed is gone",
+        );
+        assert_eq!(fetch_log_json(&entry), json!({
+            "fetched": "2019-04-01T07:30:00+00:00",
+            "status": "gone",
+            "http_code": 410,
+            "item_count": 0,
+            "error": "feed is gone",
+        }));
+    }
+
+    #[test]
+    fn test_subscription_json() {
+        let subscription = Subscription {
+            feed_id: 3,
+            title: "TechCrunch".to_owned(),
+            url: "http://techcrunch.com/feed/".to_owned(),
+            item_count: 2,
+            duplicate_of: vec![1],
+        };
+        assert_eq!(subscription_json(&subscription), json!({
+            "url": "http://techcrunch.com/feed/",
+            "title": "TechCrunch",
+            "feed_id": 3,
+            "items": 2,
+            "duplicate_of": [1],
+        }));
+    }
+
+    #[test]
+    fn test_prune_json() {
+        assert_eq!(prune_json(3, None), json!({ "pruned": 3 }));
+        assert_eq!(prune_json(3, Some(8192)), json!({ "pruned": 3, "reclaimed_bytes": 8192 }));
+        assert_eq!(prune_json(0, None).to_string(), r#"{"pruned":0}"#);
     }
 }
@@ -1,17 +1,45 @@
+use std::collections::HashMap;
+
+use chrono::NaiveDateTime;
 use diesel;
 use diesel::prelude::*;
 use diesel::pg::PgConnection;
 
 use crate::item_identity::ItemIdentifier;
 use crate::models::feed::Feed;
-use crate::models::group::Group;
+use crate::models::fetch_log::{FetchLog, NewFetchLog};
+use crate::models::group::{Group, NewGroup};
 use crate::models::item::Item;
 
+/// How many of each feed's latest items are never pruned, so they can be
+/// compared to the feed's entries to tell which are new.
+pub const LATEST_ITEMS: i64 = 10;
+
+/// The most items loaded at once for the API.
+pub const MAX_ITEMS: i64 = 50;
+
 pub fn load_groups(conn: &mut PgConnection) -> QueryResult<Vec<Group>> {
     use crate::schema::feed_group::dsl::*;
     feed_group.load(conn)
 }
 
+pub fn find_or_create_group(group_title: &str, conn: &mut PgConnection)
+-> QueryResult<Group> {
+    use crate::schema::feed_group::dsl::*;
+
+    let existing = feed_group.filter(title.eq(group_title))
+        .first(conn)
+        .optional()?;
+    match existing {
+        Some(group) => Ok(group),
+        None => {
+            diesel::insert_into(feed_group)
+                .values(&NewGroup { title: group_title })
+                .get_result(conn)
+        }
+    }
+}
+
 pub fn load_feed_groups(conn: &mut PgConnection)
 -> QueryResult<Vec<(i32, Option<i32>)>> {
     use crate::schema::feed::dsl::*;
@@ -25,6 +53,258 @@ pub fn load_feeds(conn: &mut PgConnection) -> QueryResult<Vec<Feed>> {
     feed.load(conn)
 }
 
+pub fn load_enabled_feeds(conn: &mut PgConnection) -> QueryResult<Vec<Feed>> {
+    use diesel::dsl::not;
+    use crate::schema::feed::dsl::*;
+
+    feed.filter(not(is_disabled))
+        .load(conn)
+}
+
+pub fn disable_feed(feed: &Feed, conn: &mut PgConnection) -> QueryResult<usize> {
+    use crate::schema::feed::dsl::is_disabled;
+
+    diesel::update(feed)
+        .set(is_disabled.eq(true))
+        .execute(conn)
+}
+
+pub fn schedule_feed(feed: &Feed, fetch_at: NaiveDateTime, conn: &mut PgConnection)
+-> QueryResult<usize> {
+    use crate::schema::feed::dsl::next_fetch;
+
+    diesel::update(feed)
+        .set(next_fetch.eq(fetch_at))
+        .execute(conn)
+}
+
+/// Marks the items of a feed published before the given time read,
+/// and remembers the time so older items found later start out read.
+pub fn mark_feed_read(feed_id: i32, before: NaiveDateTime, conn: &mut PgConnection)
+-> QueryResult<usize> {
+    use diesel::dsl::sql;
+    use diesel::sql_types::{Nullable, Timestamp};
+    use crate::schema::{feed, item};
+
+    conn.transaction(|conn| {
+        let count = diesel::update(item::table)
+            .filter(item::feed_id.eq(feed_id))
+            .filter(item::published.lt(before))
+            .set(item::is_read.eq(true))
+            .execute(conn)?;
+        // Never move the watermark backwards
+        diesel::update(feed::table.find(feed_id))
+            .set(feed::read_before.eq(
+                sql::<Nullable<Timestamp>>("GREATEST(read_before, ")
+                    .bind::<Timestamp, _>(before)
+                    .sql(")")
+            ))
+            .execute(conn)?;
+        Ok(count)
+    })
+}
+
+/// Marks every item of the given feeds unread, except saved items if
+/// skip_saved, and forgets when the feeds were last marked read.
+pub fn mark_feeds_unread(feed_ids: &[i32], skip_saved: bool, conn: &mut PgConnection)
+-> QueryResult<usize> {
+    use diesel::dsl::not;
+    use crate::schema::{feed, item};
+
+    conn.transaction(|conn| {
+        let query = diesel::update(item::table)
+            .filter(item::feed_id.eq_any(feed_ids))
+            .filter(item::is_read);
+        let count = if skip_saved {
+            query.filter(not(item::is_saved))
+                .set(item::is_read.eq(false))
+                .execute(conn)?
+        } else {
+            query.set(item::is_read.eq(false))
+                .execute(conn)?
+        };
+        diesel::update(feed::table.filter(feed::id.eq_any(feed_ids)))
+            .set(feed::read_before.eq(None::<NaiveDateTime>))
+            .execute(conn)?;
+        Ok(count)
+    })
+}
+
+pub fn update_feed_metadata(
+    feed: &Feed,
+    new_title: &str,
+    new_subtitle: Option<&str>,
+    new_site_url: Option<&str>,
+    conn: &mut PgConnection,
+) -> QueryResult<usize> {
+    use crate::schema::feed::dsl::{site_url, subtitle, title};
+
+    diesel::update(feed)
+        .set((
+            title.eq(new_title),
+            subtitle.eq(new_subtitle),
+            site_url.eq(new_site_url),
+        ))
+        .execute(conn)
+}
+
+pub fn set_mark_read_on_fetch(feed: &Feed, enabled: bool, conn: &mut PgConnection)
+-> QueryResult<usize> {
+    use crate::schema::feed;
+
+    diesel::update(feed::table.find(feed.id))
+        .set(feed::mark_read_on_fetch.eq(enabled))
+        .execute(conn)
+}
+
+pub fn save_backfill_url(feed: &Feed, url: Option<&str>, conn: &mut PgConnection)
+-> QueryResult<usize> {
+    use crate::schema::feed;
+
+    diesel::update(feed::table.find(feed.id))
+        .set(feed::backfill_url.eq(url))
+        .execute(conn)
+}
+
+pub fn save_feed_fingerprint(feed: &Feed, fingerprint: i64, conn: &mut PgConnection)
+-> QueryResult<usize> {
+    use crate::schema::feed;
+
+    diesel::update(feed::table.find(feed.id))
+        .set(feed::fingerprint.eq(fingerprint))
+        .execute(conn)
+}
+
+pub fn save_feed_warnings(feed: &Feed, warnings: Option<&str>, conn: &mut PgConnection)
+-> QueryResult<usize> {
+    use crate::schema::feed;
+
+    diesel::update(feed::table.find(feed.id))
+        .set(feed::last_warnings.eq(warnings))
+        .execute(conn)
+}
+
+pub fn save_feed_body(feed: &Feed, body: &[u8], conn: &mut PgConnection)
+-> QueryResult<usize> {
+    use diesel::dsl::now;
+    use crate::schema::feed_body;
+
+    diesel::insert_into(feed_body::table)
+        .values((feed_body::feed_id.eq(feed.id), feed_body::body.eq(body)))
+        .on_conflict(feed_body::feed_id)
+        .do_update()
+        .set((feed_body::body.eq(body), feed_body::fetched.eq(now)))
+        .execute(conn)
+}
+
+pub fn load_feed_bodies(conn: &mut PgConnection)
+-> QueryResult<Vec<(Feed, Vec<u8>)>> {
+    use crate::schema::{feed, feed_body};
+
+    feed::table.inner_join(feed_body::table)
+        .select((feed::all_columns, feed_body::body))
+        .load(conn)
+}
+
+pub fn load_feed(feed_id: i32, conn: &mut PgConnection)
+-> QueryResult<Option<Feed>> {
+    use crate::schema::feed::dsl::*;
+    feed.find(feed_id)
+        .first(conn)
+        .optional()
+}
+
+pub fn find_feed_by_url(feed_url: &str, conn: &mut PgConnection)
+-> QueryResult<Option<Feed>> {
+    use crate::schema::feed::dsl::*;
+    feed.filter(url.eq(feed_url))
+        .first(conn)
+        .optional()
+}
+
+/// Deletes a feed along with all of its items, including saved and
+/// archived ones, returning how many items were deleted.
+pub fn delete_feed(feed: &Feed, conn: &mut PgConnection) -> QueryResult<usize> {
+    use crate::schema::{archived_item, item};
+
+    conn.transaction(|conn| {
+        let archived_count = diesel::delete(archived_item::table)
+            .filter(archived_item::feed_id.eq(feed.id))
+            .execute(conn)?;
+        // Categories, the stored body, and the fetch log are deleted by cascade
+        let item_count = diesel::delete(item::table)
+            .filter(item::feed_id.eq(feed.id))
+            .execute(conn)?;
+        diesel::delete(feed).execute(conn)?;
+        Ok(item_count + archived_count)
+    })
+}
+
+/// Unsubscribes from a feed but keeps its saved items, which still belong
+/// to the feed, so it's disabled rather than deleted. Returns how many
+/// items were deleted.
+pub fn delete_feed_keeping_saved(feed: &Feed, conn: &mut PgConnection) -> QueryResult<usize> {
+    use diesel::dsl::not;
+    use crate::schema::{archived_item, item};
+
+    conn.transaction(|conn| {
+        let archived_count = diesel::delete(archived_item::table)
+            .filter(archived_item::feed_id.eq(feed.id))
+            .filter(not(archived_item::is_saved))
+            .execute(conn)?;
+        let item_count = diesel::delete(item::table)
+            .filter(item::feed_id.eq(feed.id))
+            .filter(not(item::is_saved))
+            .execute(conn)?;
+        disable_feed(feed, conn)?;
+        Ok(item_count + archived_count)
+    })
+}
+
+/// Records the outcome of fetching a feed, keeping only its latest entries.
+pub fn append_fetch_log(log: &NewFetchLog, keep: i64, conn: &mut PgConnection)
+-> QueryResult<()> {
+    use crate::schema::fetch_log::dsl::*;
+
+    conn.transaction(|conn| {
+        diesel::insert_into(fetch_log)
+            .values(log)
+            .execute(conn)?;
+        let oldest_kept = fetch_log.filter(feed_id.eq(log.feed_id))
+            .select(id)
+            .order(id.desc())
+            .offset(keep - 1)
+            .first::<i32>(conn)
+            .optional()?;
+        if let Some(oldest_kept) = oldest_kept {
+            diesel::delete(fetch_log.filter(feed_id.eq(log.feed_id)).filter(id.lt(oldest_kept)))
+                .execute(conn)?;
+        }
+        Ok(())
+    })
+}
+
+/// Loads the latest fetch log entries of a feed, newest first.
+pub fn load_fetch_log(feed: &Feed, limit: i64, conn: &mut PgConnection)
+-> QueryResult<Vec<FetchLog>> {
+    use crate::schema::fetch_log::dsl::*;
+
+    FetchLog::belonging_to(feed)
+        .order(id.desc())
+        .limit(limit)
+        .load(conn)
+}
+
+pub fn load_feed_items(feed: &Feed, conn: &mut PgConnection)
+-> QueryResult<Vec<Item>> {
+    use crate::schema::item::dsl::*;
+
+    item.filter(feed_id.eq(feed.id))
+        .order(id.desc())
+        .limit(50)
+        .load(conn)
+}
+
 pub enum ItemsQuery<'a> {
     Latest,
     Before(i32),
@@ -36,7 +316,7 @@ pub fn load_items(query_type: ItemsQuery, conn: &mut PgConnection)
 -> QueryResult<Vec<Item>> {
     use crate::schema::item::dsl::*;
 
-    let query = item.limit(50);
+    let query = item.limit(MAX_ITEMS);
     match query_type {
         ItemsQuery::Latest => {
             query.order(id.desc())
@@ -53,12 +333,47 @@ pub fn load_items(query_type: ItemsQuery, conn: &mut PgConnection)
                 .load(conn)
         },
         ItemsQuery::ForIds(ids) => {
-            query.filter(id.eq_any(ids))
-                .load(conn)
+            let mut items: Vec<Item> = query.filter(id.eq_any(ids))
+                .load(conn)?;
+            // Saved items may have been archived, but can still be requested
+            if items.len() < ids.len() {
+                let limit = MAX_ITEMS - items.len() as i64;
+                let archived_items = load_archived_items(ids, limit, conn)?;
+                items.extend(archived_items);
+            }
+            Ok(items)
         },
     }
 }
 
+fn load_archived_items(ids: &[i32], limit: i64, conn: &mut PgConnection)
+-> QueryResult<Vec<Item>> {
+    use crate::schema::archived_item::dsl::*;
+
+    archived_item.filter(id.eq_any(ids))
+        .limit(limit)
+        .load(conn)
+}
+
+pub fn load_item_categories(item_ids: &[i32], conn: &mut PgConnection)
+-> QueryResult<Vec<(i32, String)>> {
+    use crate::schema::{archived_item_category, item_category};
+
+    let mut categories: Vec<(i32, String)> = item_category::table
+        .filter(item_category::item_id.eq_any(item_ids))
+        .order((item_category::item_id, item_category::term))
+        .select((item_category::item_id, item_category::term))
+        .load(conn)?;
+    // Archived items keep their categories in a table of their own
+    let archived_categories = archived_item_category::table
+        .filter(archived_item_category::item_id.eq_any(item_ids))
+        .order((archived_item_category::item_id, archived_item_category::term))
+        .select((archived_item_category::item_id, archived_item_category::term))
+        .load(conn)?;
+    categories.extend(archived_categories);
+    Ok(categories)
+}
+
 pub fn load_unread_item_ids(conn: &mut PgConnection) -> QueryResult<Vec<i32>> {
     use diesel::dsl::not;
     use crate::schema::item::dsl::*;
@@ -68,12 +383,53 @@ pub fn load_unread_item_ids(conn: &mut PgConnection) -> QueryResult<Vec<i32>> {
         .load::<i32>(conn)
 }
 
+/// Loads the latest unread items across every feed, with their feeds.
+pub fn load_unread_items(limit: i64, conn: &mut PgConnection)
+-> QueryResult<Vec<(Item, Feed)>> {
+    use diesel::dsl::not;
+    use crate::schema::{feed, item};
+
+    item::table.inner_join(feed::table)
+        .filter(not(item::is_read))
+        .order(item::id.desc())
+        .limit(limit)
+        .load(conn)
+}
+
 pub fn load_saved_item_ids(conn: &mut PgConnection) -> QueryResult<Vec<i32>> {
+    use crate::schema::{archived_item, item};
+
+    let mut ids = item::table.filter(item::is_saved)
+        .select(item::id)
+        .load::<i32>(conn)?;
+    let archived_ids = archived_item::table.filter(archived_item::is_saved)
+        .select(archived_item::id)
+        .load::<i32>(conn)?;
+    ids.extend(archived_ids);
+    Ok(ids)
+}
+
+pub fn count_unread_items_by_feed(conn: &mut PgConnection)
+-> QueryResult<Vec<(i32, i64)>> {
+    use diesel::dsl::{count_star, not};
     use crate::schema::item::dsl::*;
 
-    item.filter(is_saved)
-        .select(id)
-        .load::<i32>(conn)
+    item.filter(not(is_read))
+        .group_by(feed_id)
+        .select((feed_id, count_star()))
+        .load(conn)
+}
+
+pub fn count_unread_items_by_group(conn: &mut PgConnection)
+-> QueryResult<Vec<(Option<i32>, i64)>> {
+    use diesel::dsl::{count_star, not};
+    use crate::schema::{feed, item};
+
+    item::table.inner_join(feed::table)
+        .filter(not(item::is_read))
+        .group_by(feed::group_id)
+        .select((feed::group_id, count_star()))
+        .load(conn)
 }
 
 pub fn count_items(conn: &mut PgConnection) -> QueryResult<u32> {
@@ -83,25 +439,56 @@ pub fn count_items(conn: &mut PgConnection) -> QueryResult<u32> {
     query.get_result::<i64>(conn).map(|i| i as u32)
 }
 
+/// Loads the identifiers of the latest items of a feed,
+/// along with when each was last known to be updated.
 pub fn load_latest_item_identifiers(feed: &Feed, conn: &mut PgConnection)
--> QueryResult<Vec<ItemIdentifier<'static>>> {
+-> QueryResult<Vec<(ItemIdentifier<'static>, Option<NaiveDateTime>)>> {
     use crate::schema::item;
 
     let results = item::table.filter(item::feed_id.eq(feed.id))
         .order(item::id.desc())
-        .limit(10)
-        .select((item::url, item::guid))
-        .load::<(Option<String>, Option<String>)>(conn)?;
+        .limit(LATEST_ITEMS)
+        .select((item::url, item::guid, item::updated))
+        .load::<(Option<String>, Option<String>, Option<NaiveDateTime>)>(conn)?;
 
     let identifiers = results
         .into_iter()
         // Database ensures that both cannot be null, so this is safe
-        .map(|(url, guid)| ItemIdentifier::new_owned(url, guid).unwrap())
+        .map(|(url, guid, updated)| (ItemIdentifier::new_owned(url, guid).unwrap(), updated))
         .collect();
 
     Ok(identifiers)
 }
 
+#[derive(QueryableByName)]
+#[diesel(table_name = crate::schema::item)]
+struct LatestItem {
+    feed_id: i32,
+    url: Option<String>,
+    guid: Option<String>,
+}
+
+/// Loads the identifiers of the latest items of every feed except the given
+/// one at once, like `load_latest_item_identifiers` for each feed.
+pub fn load_other_feeds_latest_item_identifiers(feed: &Feed, conn: &mut PgConnection)
+-> QueryResult<HashMap<i32, Vec<ItemIdentifier<'static>>>> {
+    use diesel::sql_types::{BigInt, Integer};
+
+    let query = include_str!("latest_items.sql");
+    let results = diesel::sql_query(query)
+        .bind::<Integer, _>(feed.id)
+        .bind::<BigInt, _>(LATEST_ITEMS)
+        .load::<LatestItem>(conn)?;
+
+    let mut identifiers = HashMap::<_, Vec<_>>::new();
+    for LatestItem { feed_id, url, guid } in results {
+        // Database ensures that both cannot be null, so this is safe
+        let identifier = ItemIdentifier::new_owned(url, guid).unwrap();
+        identifiers.entry(feed_id).or_default().push(identifier);
+    }
+    Ok(identifiers)
+}
+
 pub fn item_already_exists(
     identifier: &ItemIdentifier,
     feed: &Feed,
@@ -124,8 +511,232 @@ pub fn item_already_exists(
         .get_result(conn)
 }
 
+/// Sets when an existing item was updated, unless it's already known to have
+/// been updated since.
+pub fn refresh_item_updated(
+    identifier: &ItemIdentifier,
+    feed: &Feed,
+    date: NaiveDateTime,
+    conn: &mut PgConnection,
+) -> QueryResult<usize> {
+    use crate::schema::item::dsl::*;
+
+    let http_link = identifier.link().map(|s| s.replace("https://", "http://"));
+    let https_link = identifier.link().map(|s| s.replace("http://", "https://"));
+
+    let identity_expr = url.eq(http_link)
+        .or(url.eq(https_link))
+        .or(guid.eq(identifier.guid()));
+
+    let query = item.filter(feed_id.eq(feed.id).and(identity_expr))
+        .filter(updated.is_null().or(updated.lt(date)));
+    diesel::update(query)
+        .set(updated.eq(date))
+        .execute(conn)
+}
+
 pub fn prune_read_items(conn: &mut PgConnection) -> QueryResult<usize> {
+    use diesel::sql_types::BigInt;
+
     let query = include_str!("prune.sql");
     diesel::sql_query(query)
+        .bind::<BigInt, _>(LATEST_ITEMS)
         .execute(conn)
 }
+
+/// Moves saved items published before the given date out of the item table,
+/// except for the latest of each feed which are needed to detect new items.
+/// Their categories are moved along with them.
+pub fn archive_saved_items(before: NaiveDateTime, conn: &mut PgConnection)
+-> QueryResult<usize> {
+    use diesel::sql_types::{BigInt, Timestamp};
+
+    let query = include_str!("archive.sql");
+    diesel::sql_query(query)
+        .bind::<Timestamp, _>(before)
+        .bind::<BigInt, _>(LATEST_ITEMS)
+        .execute(conn)
+}
+
+fn item_table_size(conn: &mut PgConnection) -> QueryResult<i64> {
+    use diesel::dsl::sql;
+    use diesel::sql_types::BigInt;
+
+    diesel::select(sql::<BigInt>("pg_total_relation_size('item')"))
+        .get_result(conn)
+}
+
+/// Compacts the item table, returning how many bytes were reclaimed.
+/// This can't run in a transaction.
+pub fn vacuum_items(conn: &mut PgConnection) -> QueryResult<i64> {
+    let size_before = item_table_size(conn)?;
+    // Unlike a plain VACUUM, FULL returns the freed space to the OS
+    diesel::sql_query("VACUUM FULL ANALYZE item")
+        .execute(conn)?;
+    let size_after = item_table_size(conn)?;
+    // A table with nothing to free can grow slightly from its new statistics
+    Ok((size_before - size_after).max(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use diesel::prelude::*;
+
+    use crate::schema::{item, item_category};
+    use crate::testing;
+    use super::{
+        ItemsQuery, LATEST_ITEMS, archive_saved_items, delete_feed, delete_feed_keeping_saved,
+        find_or_create_group, load_feed, load_feed_items, load_groups, load_item_categories,
+        load_items, mark_feed_read, mark_feeds_unread, prune_read_items, vacuum_items,
+    };
+
+    #[test]
+    fn test_find_or_create_group() {
+        let Some(mut conn) = testing::connection() else { return };
+        let tech = find_or_create_group("Tech", &mut conn).unwrap();
+        assert_eq!(find_or_create_group("Tech", &mut conn).unwrap().id, tech.id);
+        let news = find_or_create_group("News", &mut conn).unwrap();
+        assert_ne!(news.id, tech.id);
+        assert_eq!(load_groups(&mut conn).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_archive_saved_items() {
+        let Some(mut conn) = testing::connection() else { return };
+        let feed = testing::insert_feed("http://example.com/feed", "Example", &mut conn);
+        let saved = testing::insert_item(&feed, "Saved", 1, &mut conn);
+        let unsaved = testing::insert_item(&feed, "Unsaved", 1, &mut conn);
+        for day in 2..(2 + LATEST_ITEMS as u32) {
+            testing::insert_item(&feed, &format!("Latest {}", day), day, &mut conn);
+        }
+        diesel::update(item::table.filter(item::id.eq(saved.id)))
+            .set(item::is_saved.eq(true))
+            .execute(&mut conn)
+            .unwrap();
+        diesel::insert_into(item_category::table)
+            .values((item_category::item_id.eq(saved.id), item_category::term.eq("cars")))
+            .execute(&mut conn)
+            .unwrap();
+
+        let count = archive_saved_items(testing::april(20), &mut conn).unwrap();
+        assert_eq!(count, 1);
+        let remaining: Vec<i32> = item::table.select(item::id).load(&mut conn).unwrap();
+        assert!(!remaining.contains(&saved.id));
+        assert!(remaining.contains(&unsaved.id));
+
+        // Archived items can still be requested by id, along with their categories
+        let ids = [saved.id, unsaved.id];
+        let mut items = load_items(ItemsQuery::ForIds(&ids), &mut conn).unwrap();
+        items.sort_by_key(|item| item.id);
+        let titles: Vec<_> = items.iter().map(|item| (item.title.as_str(), item.is_saved)).collect();
+        assert_eq!(titles, [("Saved", true), ("Unsaved", false)]);
+        let categories = load_item_categories(&ids, &mut conn).unwrap();
+        assert_eq!(categories, [(ids[0], "cars".to_owned())]);
+    }
+
+    #[test]
+    fn test_delete_feed() {
+        let Some(mut conn) = testing::connection() else { return };
+        let feed = testing::insert_feed("http://example.com/feed", "Example", &mut conn);
+        let saved = testing::insert_item(&feed, "Saved", 1, &mut conn);
+        testing::insert_item(&feed, "Unread", 2, &mut conn);
+        diesel::update(item::table.filter(item::id.eq(saved.id)))
+            .set(item::is_saved.eq(true))
+            .execute(&mut conn)
+            .unwrap();
+
+        assert_eq!(delete_feed_keeping_saved(&feed, &mut conn).unwrap(), 1);
+        let kept = load_feed(feed.id, &mut conn).unwrap().unwrap();
+        assert!(kept.is_disabled);
+        let titles: Vec<_> = load_feed_items(&kept, &mut conn).unwrap()
+            .into_iter()
+            .map(|item| item.title)
+            .collect();
+        assert_eq!(titles, ["Saved"]);
+
+        assert_eq!(delete_feed(&kept, &mut conn).unwrap(), 1);
+        assert!(load_feed(feed.id, &mut conn).unwrap().is_none());
+        assert!(load_feed_items(&feed, &mut conn).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_mark_feeds_unread() {
+        let Some(mut conn) = testing::connection() else { return };
+        let feed = testing::insert_feed("http://example.com/feed", "Example", &mut conn);
+        let saved = testing::insert_item(&feed, "Saved", 1, &mut conn);
+        testing::insert_item(&feed, "Unsaved", 2, &mut conn);
+        diesel::update(item::table.filter(item::id.eq(saved.id)))
+            .set(item::is_saved.eq(true))
+            .execute(&mut conn)
+            .unwrap();
+        let read_state = |conn: &mut PgConnection| -> Vec<(String, bool)> {
+            load_feed_items(&feed, conn).unwrap()
+                .into_iter()
+                .map(|item| (item.title, item.is_read))
+                .collect()
+        };
+
+        assert_eq!(mark_feed_read(feed.id, testing::april(3), &mut conn).unwrap(), 2);
+        assert_eq!(mark_feeds_unread(&[feed.id], true, &mut conn).unwrap(), 1);
+        assert_eq!(read_state(&mut conn), [("Unsaved".to_owned(), false), ("Saved".to_owned(), true)]);
+        // Later items shouldn't start out read anymore
+        assert_eq!(load_feed(feed.id, &mut conn).unwrap().unwrap().read_before, None);
+
+        assert_eq!(mark_feeds_unread(&[feed.id], false, &mut conn).unwrap(), 1);
+        assert_eq!(read_state(&mut conn), [("Unsaved".to_owned(), false), ("Saved".to_owned(), false)]);
+    }
+
+    #[test]
+    fn test_prune_keeps_saved() {
+        let Some(mut conn) = testing::connection() else { return };
+        let feed = testing::insert_feed("http://example.com/feed", "Example", &mut conn);
+        let saved = testing::insert_item(&feed, "Saved", 1, &mut conn);
+        let unsaved = testing::insert_item(&feed, "Unsaved", 1, &mut conn);
+        for day in 2..(2 + LATEST_ITEMS as u32) {
+            testing::insert_item(&feed, &format!("Latest {}", day), day, &mut conn);
+        }
+        diesel::update(item::table.filter(item::id.eq_any([saved.id, unsaved.id])))
+            .set(item::is_read.eq(true))
+            .execute(&mut conn)
+            .unwrap();
+        diesel::update(item::table.filter(item::id.eq(saved.id)))
+            .set(item::is_saved.eq(true))
+            .execute(&mut conn)
+            .unwrap();
+
+        assert_eq!(prune_read_items(&mut conn).unwrap(), 1);
+        let remaining = |id: i32, conn: &mut PgConnection| {
+            item::table.find(id).count().get_result::<i64>(conn).unwrap()
+        };
+        assert_eq!(remaining(saved.id, &mut conn), 1);
+        assert_eq!(remaining(unsaved.id, &mut conn), 0);
+    }
+
+    #[test]
+    fn test_vacuum_items() {
+        let Some(mut conn) = testing::committed_connection("vacuum_test") else { return };
+        let feed = testing::insert_feed("http://example.com/feed", "Example", &mut conn);
+
+        let mut reclaimed = Vec::new();
+        for cycle in 1..=3 {
+            let day = cycle * 2;
+            for i in 0..100 {
+                testing::insert_item(&feed, &format!("Old {} {}", cycle, i), day - 1, &mut conn);
+            }
+            for i in 0..LATEST_ITEMS {
+                testing::insert_item(&feed, &format!("New {} {}", cycle, i), day, &mut conn);
+            }
+            diesel::update(item::table.filter(item::published.eq(testing::april(day - 1))))
+                .set((item::is_read.eq(true), item::content.eq("x".repeat(2000))))
+                .execute(&mut conn)
+                .unwrap();
+
+            assert_eq!(prune_read_items(&mut conn).unwrap(), 100);
+            reclaimed.push(vacuum_items(&mut conn).unwrap());
+        }
+        // Each cycle frees the space of the items it pruned, so the table doesn't keep growing
+        assert!(reclaimed.iter().all(|&bytes| bytes > 0), "{:?}", reclaimed);
+        // Nothing was pruned since the last vacuum
+        assert_eq!(vacuum_items(&mut conn).unwrap(), 0);
+    }
+}
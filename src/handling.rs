@@ -1,5 +1,7 @@
 use std::collections::HashMap;
 
+use chrono::NaiveDateTime;
+
 use diesel;
 use diesel::prelude::*;
 use diesel::pg::PgConnection;
@@ -18,18 +20,24 @@ use crate::error::Error;
 use crate::models::feed::Feed as DbFeed;
 use crate::models::group::Group as DbGroup;
 use crate::models::item::Item as DbItem;
+use crate::parse::preview_text;
+use crate::sanitize::sanitize_html;
 
 type DataResult<T> = Result<T, Error<diesel::result::Error>>;
 
-fn format_group(group: DbGroup) -> fever_api::Group {
+fn format_group(group: DbGroup, unread_counts: &HashMap<i32, u32>)
+-> fever_api::Group {
     fever_api::Group {
+        unread_count: Some(unread_counts.get(&group.id).copied().unwrap_or(0)),
         id: group.id as u32,
         title: group.title,
     }
 }
 
-fn format_feed(feed: DbFeed) -> fever_api::Feed {
+fn format_feed(feed: DbFeed, unread_counts: &HashMap<i32, u32>)
+-> fever_api::Feed {
     fever_api::Feed {
+        unread_count: Some(unread_counts.get(&feed.id).copied().unwrap_or(0)),
         id: feed.id as u32,
         title: feed.title,
         url: feed.url,
@@ -53,25 +61,56 @@ fn format_feeds_groups(feed_groups: impl Iterator<Item=(i32, Option<i32>)>)
         .collect()
 }
 
-fn format_item(item: DbItem) -> fever_api::Item {
+/// Options for how items are presented to API clients.
+#[derive(Clone, Copy, Default)]
+pub struct ResponseOptions {
+    /// Whether to remove scripts and other unsafe markup from item html.
+    pub sanitize_html: bool,
+}
+
+/// The most characters in an item's preview.
+const PREVIEW_CHARS: usize = 200;
+
+fn format_item(
+    item: DbItem,
+    categories: &mut HashMap<i32, Vec<String>>,
+    options: ResponseOptions,
+) -> fever_api::Item {
+    // The content already falls back to the summary when the item is parsed
+    let preview = preview_text(item.summary.as_deref().unwrap_or(""), &item.content, Some(PREVIEW_CHARS));
+    let html = if options.sanitize_html {
+        sanitize_html(&item.content)
+    } else {
+        item.content
+    };
+
     fever_api::Item {
         id: item.id as u32,
         feed_id: item.feed_id as u32,
         title: item.title,
         author: item.author,
         url: item.url,
-        html: item.content,
+        html,
         is_saved: item.is_saved,
         is_read: item.is_read,
         created_on_time: item.published,
+        updated_on_time: item.updated,
+        categories: categories.remove(&item.id).unwrap_or_default(),
+        preview,
     }
 }
 
 fn load_groups(conn: &mut PgConnection) -> DataResult<ApiResponsePayload> {
+    let unread_counts = data::count_unread_items_by_group(conn)
+        .map_err(fill_err!("Error counting unread items"))?
+        .into_iter()
+        .filter_map(|(group_id, count)| group_id.map(|id| (id, count as u32)))
+        .collect();
+
     let groups = data::load_groups(conn)
         .map_err(fill_err!("Error loading groups"))?
         .into_iter()
-        .map(format_group)
+        .map(|group| format_group(group, &unread_counts))
         .collect();
 
     let feed_groups = data::load_feed_groups(conn)
@@ -92,9 +131,15 @@ fn load_feeds(conn: &mut PgConnection) -> DataResult<ApiResponsePayload> {
         format_feeds_groups(feed_groups)
     };
 
+    let unread_counts = data::count_unread_items_by_feed(conn)
+        .map_err(fill_err!("Error counting unread items"))?
+        .into_iter()
+        .map(|(feed_id, count)| (feed_id, count as u32))
+        .collect();
+
     let feeds = feeds
         .into_iter()
-        .map(format_feed)
+        .map(|feed| format_feed(feed, &unread_counts))
         .collect();
 
     Ok(ApiResponsePayload::Feeds {
@@ -103,12 +148,20 @@ fn load_feeds(conn: &mut PgConnection) -> DataResult<ApiResponsePayload> {
     })
 }
 
-fn load_items(query: ItemsQuery, conn: &mut PgConnection)
+fn load_items(query: ItemsQuery, options: ResponseOptions, conn: &mut PgConnection)
 -> DataResult<ApiResponsePayload> {
     let items = data::load_items(query, conn)
-        .map_err(fill_err!("Error loading items"))?
-        .into_iter()
-        .map(format_item)
+        .map_err(fill_err!("Error loading items"))?;
+
+    let item_ids: Vec<i32> = items.iter().map(|item| item.id).collect();
+    let mut categories: HashMap<i32, Vec<String>> = HashMap::new();
+    for (item_id, term) in data::load_item_categories(&item_ids, conn)
+            .map_err(fill_err!("Error loading item categories"))? {
+        categories.entry(item_id).or_default().push(term);
+    }
+
+    let items = items.into_iter()
+        .map(|item| format_item(item, &mut categories, options))
         .collect();
     let total_items = data::count_items(conn)
         .map_err(fill_err!("Error counting items"))?;
@@ -157,23 +210,23 @@ fn update_item_read(id: u32, is_read: bool, conn: &mut PgConnection)
 
 fn update_item_saved(id: u32, is_saved: bool, conn: &mut PgConnection)
 -> DataResult<ApiResponsePayload> {
-    use crate::schema::item;
+    use crate::schema::{archived_item, item};
 
     diesel::update(item::table.find(id as i32))
         .set(item::is_saved.eq(is_saved))
         .execute(conn)
         .map_err(fill_err!("Error updating item is_saved"))?;
+    diesel::update(archived_item::table.find(id as i32))
+        .set(archived_item::is_saved.eq(is_saved))
+        .execute(conn)
+        .map_err(fill_err!("Error updating archived item is_saved"))?;
 
     load_saved_item_ids(conn)
 }
 
-fn mark_feed_read(id: u32, conn: &mut PgConnection)
+fn mark_feed_read(id: u32, before: NaiveDateTime, conn: &mut PgConnection)
 -> DataResult<ApiResponsePayload> {
-    use crate::schema::item;
-
-    diesel::update(item::table.filter(item::feed_id.eq(id as i32)))
-        .set(item::is_read.eq(true))
-        .execute(conn)
+    data::mark_feed_read(id as i32, before, conn)
         .map_err(fill_err!("Error marking feed read"))?;
 
     load_unread_item_ids(conn)
@@ -182,35 +235,32 @@ fn mark_feed_read(id: u32, conn: &mut PgConnection)
 pub fn handle_api_request(
     request: &ApiRequest,
     expected_key: Option<&ApiKey>,
+    options: ResponseOptions,
     conn: &mut PgConnection,
 ) -> DataResult<ApiResponse> {
-    let mut response = ApiResponse {
-        api_version: 1,
-        auth: false,
-        last_refreshed_on_time: None,
-        payload: ApiResponsePayload::None {},
-    };
-
     if !expected_key.map_or(true, |key| request.api_key == *key) {
-        return Ok(response);
+        return Ok(ApiResponse::unauthorized());
     }
-    response.auth = true;
 
-    response.payload = match request.req_type {
+    let payload = match request.req_type {
         ApiRequestType::Groups => load_groups(conn)?,
         ApiRequestType::Feeds => load_feeds(conn)?,
         ApiRequestType::LatestItems => {
-            load_items(ItemsQuery::Latest, conn)?
+            load_items(ItemsQuery::Latest, options, conn)?
         },
         ApiRequestType::ItemsBefore(id) => {
-            load_items(ItemsQuery::Before(id as i32), conn)?
+            load_items(ItemsQuery::Before(id as i32), options, conn)?
         },
         ApiRequestType::ItemsSince(id) => {
-            load_items(ItemsQuery::After(id as i32), conn)?
+            load_items(ItemsQuery::After(id as i32), options, conn)?
         },
+        // Parsing already refuses this many ids, but requests can be built directly
+        ApiRequestType::Items(ref ids) if ids.len() > fever_api::MAX_ITEM_IDS => {
+            ApiResponsePayload::None {}
+        }
         ApiRequestType::Items(ref ids) => {
             let ids: Vec<_> = ids.iter().map(|&i| i as i32).collect();
-            load_items(ItemsQuery::ForIds(&ids), conn)?
+            load_items(ItemsQuery::ForIds(&ids), options, conn)?
         }
         ApiRequestType::UnreadItems => load_unread_item_ids(conn)?,
         ApiRequestType::SavedItems => load_saved_item_ids(conn)?,
@@ -218,9 +268,142 @@ pub fn handle_api_request(
         ApiRequestType::MarkItemUnread(id) => update_item_read(id, false, conn)?,
         ApiRequestType::MarkItemSaved(id) => update_item_saved(id, true, conn)?,
         ApiRequestType::MarkItemUnsaved(id) => update_item_saved(id, false, conn)?,
-        ApiRequestType::MarkFeedRead(id, _) => mark_feed_read(id, conn)?,
+        ApiRequestType::MarkFeedRead(id, before) => mark_feed_read(id, before, conn)?,
         _ => ApiResponsePayload::None {},
     };
 
-    Ok(response)
+    Ok(ApiResponse::new(true, payload))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use diesel::prelude::*;
+    use serde_json::json;
+
+    use crate::data;
+    use crate::models::item::Item as DbItem;
+    use crate::schema::{feed, item};
+    use crate::testing;
+    use fever_api::{Key, MAX_ITEM_IDS, Request, RequestType};
+
+    use super::{
+        PREVIEW_CHARS, ResponseOptions, format_item, handle_api_request, load_feeds, load_groups,
+    };
+
+    fn item(content: &str) -> DbItem {
+        let feed = testing::feed(1, "http://techcrunch.com/feed/");
+        DbItem {
+            content: content.to_owned(),
+            ..testing::item(1, &feed, "Ford hires Elon Musk as CEO", 1)
+        }
+    }
+
+    #[test]
+    fn test_format_item_sanitized() {
+        let content = "<p>Ford hires Elon Musk.</p><script>steal()</script>";
+
+        let formatted = format_item(item(content), &mut HashMap::new(), ResponseOptions::default());
+        assert_eq!(formatted.html, content);
+
+        let options = ResponseOptions { sanitize_html: true };
+        let formatted = format_item(item(content), &mut HashMap::new(), options);
+        assert_eq!(formatted.html, "<p>Ford hires Elon Musk.</p>");
+    }
+
+    #[test]
+    fn test_format_item_preview() {
+        // A summary-only entry is stored as content without a separate summary
+        let summary_only = item("<p>Ford hires Elon Musk.</p>");
+        let formatted = format_item(summary_only, &mut HashMap::new(), ResponseOptions::default());
+        assert_eq!(formatted.html, "<p>Ford hires Elon Musk.</p>");
+        assert_eq!(formatted.preview.as_deref(), Some("Ford hires Elon Musk."));
+
+        let content = format!("<p>{}</p>", "Ford hires Elon Musk. ".repeat(20));
+        let content_only = item(&content);
+        let formatted = format_item(content_only, &mut HashMap::new(), ResponseOptions::default());
+        assert_eq!(formatted.html, content);
+        let preview = formatted.preview.unwrap();
+        assert_eq!(preview.chars().count(), PREVIEW_CHARS);
+        assert!(preview.starts_with("Ford hires Elon Musk. Ford"));
+        assert!(preview.ends_with('…'));
+
+        let mut summarized = item(&content);
+        summarized.summary = Some("<b>Musk</b> joins Ford".to_owned());
+        let formatted = format_item(summarized, &mut HashMap::new(), ResponseOptions::default());
+        assert_eq!(formatted.html, content);
+        assert_eq!(formatted.preview.as_deref(), Some("Musk joins Ford"));
+
+        let formatted = format_item(item(""), &mut HashMap::new(), ResponseOptions::default());
+        assert_eq!(formatted.preview, None);
+    }
+
+    #[test]
+    fn test_format_item_dates() {
+        let mut updated_item = item("");
+        let updated = updated_item.published + chrono::Duration::days(1);
+        updated_item.updated = Some(updated);
+
+        let formatted = format_item(updated_item, &mut HashMap::new(), ResponseOptions::default());
+        assert_eq!(formatted.created_on_time.timestamp(), 1554103800);
+        assert_eq!(formatted.updated_on_time.map(|date| date.timestamp()), Some(1554190200));
+    }
+
+    #[test]
+    fn test_unread_counts() {
+        let Some(mut conn) = testing::connection() else { return };
+        let tech = data::find_or_create_group("Tech", &mut conn).unwrap();
+        let techcrunch = testing::insert_feed("http://techcrunch.com/feed/", "TechCrunch", &mut conn);
+        let xkcd = testing::insert_feed("https://xkcd.com/rss.xml", "xkcd", &mut conn);
+        diesel::update(feed::table.find(techcrunch.id))
+            .set(feed::group_id.eq(tech.id))
+            .execute(&mut conn)
+            .unwrap();
+        testing::insert_item(&techcrunch, "Ford hires Elon Musk", 1, &mut conn);
+        testing::insert_item(&techcrunch, "Tesla stock falls", 2, &mut conn);
+        let read = testing::insert_item(&techcrunch, "Elon Musk leaves Ford", 3, &mut conn);
+        diesel::update(item::table.find(read.id))
+            .set(item::is_read.eq(true))
+            .execute(&mut conn)
+            .unwrap();
+
+        let groups = serde_json::to_value(load_groups(&mut conn).unwrap()).unwrap();
+        assert_eq!(groups["groups"], json!([{ "id": tech.id, "title": "Tech", "unread_count": 2 }]));
+
+        let feeds = serde_json::to_value(load_feeds(&mut conn).unwrap()).unwrap();
+        let counts: Vec<_> = feeds["feeds"].as_array().unwrap().iter()
+            .map(|feed| (feed["id"].as_i64().unwrap() as i32, feed["unread_count"].clone()))
+            .collect();
+        assert!(counts.contains(&(techcrunch.id, json!(2))));
+        // Feeds without unread items still have a count rather than none
+        assert!(counts.contains(&(xkcd.id, json!(0))));
+    }
+
+    #[test]
+    fn test_items_with_ids() {
+        let Some(mut conn) = testing::connection() else { return };
+        let feed = testing::insert_feed("http://techcrunch.com/feed/", "TechCrunch", &mut conn);
+        let ids: Vec<_> = (1..=4)
+            .map(|day| testing::insert_item(&feed, &format!("Item {}", day), day, &mut conn).id as u32)
+            .collect();
+        let item_ids = |ids: Vec<u32>, conn: &mut PgConnection| -> Option<Vec<u32>> {
+            let request = Request { req_type: RequestType::Items(ids), api_key: Key::new("user", "pass") };
+            let response = handle_api_request(&request, None, ResponseOptions::default(), conn).unwrap();
+            let response = serde_json::to_value(response).unwrap();
+            let items = response.get("items")?.as_array().unwrap().iter()
+                .map(|item| item["id"].as_u64().unwrap() as u32)
+                .collect();
+            Some(items)
+        };
+
+        let requested = vec![ids[0], ids[2], ids[3]];
+        let mut returned = item_ids(requested.clone(), &mut conn).unwrap();
+        returned.sort();
+        assert_eq!(returned, requested);
+
+        // More ids than the API allows get no items at all
+        let too_many = (0..=MAX_ITEM_IDS as u32).map(|i| ids[0] + i).collect();
+        assert_eq!(item_ids(too_many, &mut conn), None);
+    }
 }
@@ -0,0 +1,26 @@
+use std::fmt::Write;
+
+use md5::{Digest, Md5};
+
+/// Finishes an md5 hash and formats it as lowercase hex.
+pub fn md5_hex(hash: Md5) -> String {
+    let mut hex = String::with_capacity(32);
+    for byte in hash.finalize() {
+        let _ = write!(hex, "{:02x}", byte);
+    }
+    hex
+}
+
+#[cfg(test)]
+mod tests {
+    use md5::{Digest, Md5};
+
+    use super::md5_hex;
+
+    #[test]
+    fn test_md5_hex() {
+        assert_eq!(md5_hex(Md5::new()), "d41d8cd98f00b204e9800998ecf8427e");
+        // Leading zeros of each byte are kept
+        assert_eq!(md5_hex(Md5::new_with_prefix("a")), "0cc175b9c0f1b6a831c399e269772661");
+    }
+}
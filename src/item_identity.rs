@@ -1,11 +1,47 @@
 use std::borrow::Cow;
 
+use chrono::{DateTime, FixedOffset};
+use md5::{Digest, Md5};
+
+use crate::hash::md5_hex;
+
 fn eq_ignoring_scheme(a: &str, b: &str) -> bool {
     a == b
         || a.strip_prefix("https://").map_or(false, |a| Some(a) == b.strip_prefix("http://"))
         || a.strip_prefix("http://").map_or(false, |a| Some(a) == b.strip_prefix("https://"))
 }
 
+/// Builds a stable urn for something without an id from an md5 of its parts,
+/// separated by newlines. This is the only scheme for made up ids, whether
+/// they're stored as guids or published as Atom ids.
+pub fn synthesize_id(parts: &[&str]) -> String {
+    let mut hash = Md5::new();
+    for (i, part) in parts.iter().enumerate() {
+        if i > 0 {
+            hash.update("\n");
+        }
+        hash.update(part);
+    }
+
+    format!("urn:feeds:md5:{}", md5_hex(hash))
+}
+
+/// Builds a stable guid for an entry that has neither a guid nor a link,
+/// so that it's still recognized when its feed is fetched again.
+/// This hashes the feed id, title, and published date, so entries that
+/// reuse a title are told apart. Undated entries use their content instead,
+/// though an edit makes them look new.
+pub fn synthesize_guid(
+    feed_id: i32,
+    title: &str,
+    published: Option<DateTime<FixedOffset>>,
+    content: &str,
+) -> String {
+    let published = published.map(|published| published.to_rfc3339());
+    let date_or_content = published.as_deref().unwrap_or(content);
+    synthesize_id(&[&feed_id.to_string(), title, date_or_content])
+}
+
 #[derive(Clone, Debug)]
 pub enum ItemIdentifier<'a> {
     Link(Cow<'a, str>),
@@ -55,3 +91,35 @@ impl<'a> PartialEq for ItemIdentifier<'a> {
             || self.link().zip(other.link()).map_or(false, |(l1, l2)| eq_ignoring_scheme(l1, l2))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::DateTime;
+
+    use super::{ItemIdentifier, synthesize_guid};
+
+    #[test]
+    fn test_synthesize_guid() {
+        let title = "Ford hires Elon Musk as CEO";
+        let guid = synthesize_guid(1, title, None, "");
+        assert!(guid.starts_with("urn:feeds:md5:"));
+        assert_eq!(guid, synthesize_guid(1, title, None, ""));
+        assert_ne!(guid, synthesize_guid(2, title, None, ""));
+        assert_ne!(guid, synthesize_guid(1, "Ford stock rises", None, ""));
+
+        // Recurring posts with the same title are told apart by date or content
+        let monday = DateTime::parse_from_rfc3339("2019-04-01T07:30:00Z").ok();
+        let tuesday = DateTime::parse_from_rfc3339("2019-04-02T07:30:00Z").ok();
+        let daily = "Daily briefing";
+        assert_ne!(synthesize_guid(1, daily, monday, ""), synthesize_guid(1, daily, tuesday, ""));
+        assert_ne!(synthesize_guid(1, daily, None, "Monday"), synthesize_guid(1, daily, None, "Tuesday"));
+        // When there's a date, edits to the content don't change the guid
+        assert_eq!(synthesize_guid(1, daily, monday, "Monday"), synthesize_guid(1, daily, monday, "Edited"));
+
+        let refetched_guid = synthesize_guid(1, title, None, "");
+        assert_eq!(
+            ItemIdentifier::new(None, Some(&guid)),
+            ItemIdentifier::new(None, Some(&refetched_guid)),
+        );
+    }
+}
@@ -0,0 +1,57 @@
+use atom_syndication::{Entry, Feed};
+use atom_syndication::extension::Extension;
+
+pub static NAMESPACE: &str = "http://www.itunes.com/dtds/podcast-1.0.dtd";
+
+fn format_duration(secs: u64) -> String {
+    format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+pub trait FeedExt {
+    /// Declares the iTunes namespace on this feed,
+    /// which is needed to serialize any iTunes extensions of its entries.
+    fn declare_itunes_namespace(&mut self);
+}
+
+impl FeedExt for Feed {
+    fn declare_itunes_namespace(&mut self) {
+        self.namespaces.insert("itunes".to_owned(), NAMESPACE.to_owned());
+    }
+}
+
+pub trait EntryExt {
+    /// Sets the duration of this entry's media as an `<itunes:duration>` extension.
+    fn add_media_duration(&mut self, secs: u64);
+}
+
+impl EntryExt for Entry {
+    fn add_media_duration(&mut self, secs: u64) {
+        let duration = Extension {
+            name: "itunes:duration".to_owned(),
+            value: Some(format_duration(secs)),
+            ..Default::default()
+        };
+        self.extensions.entry("itunes".to_owned()).or_default()
+            .insert("duration".to_owned(), vec![duration]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use atom_syndication::{Entry, Feed};
+    use super::{EntryExt, FeedExt};
+
+    #[test]
+    fn test_media_duration() {
+        let mut entry = Entry::default();
+        entry.add_media_duration(3725);
+
+        let mut feed = Feed::default();
+        feed.declare_itunes_namespace();
+        feed.set_entries(vec![entry]);
+
+        let xml = feed.to_string();
+        assert!(xml.contains(r#"xmlns:itunes="http://www.itunes.com/dtds/podcast-1.0.dtd""#));
+        assert!(xml.contains("<itunes:duration>01:02:05</itunes:duration>"));
+    }
+}
@@ -0,0 +1,11 @@
+SELECT feed_id, url, guid
+FROM (
+    SELECT
+        feed_id,
+        url,
+        guid,
+        row_number() OVER (PARTITION BY feed_id ORDER BY id DESC) AS feed_num
+    FROM item
+    WHERE feed_id <> $1
+) AS numbered_item
+WHERE feed_num <= $2;
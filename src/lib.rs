@@ -0,0 +1,13 @@
+//! Parsing and building feeds, separate from the server and database code in
+//! the binary. Being a library lets these modules export helpers that the
+//! binary doesn't call yet without them being dead code, and is why items
+//! the binary does use, like `Entry::expand_link`, are `pub` rather than
+//! `pub(crate)`.
+
+pub mod build;
+pub mod hash;
+pub mod item_identity;
+pub mod itunes;
+pub mod parse;
+pub mod prelude;
+pub mod sanitize;
@@ -7,38 +7,399 @@ mod data;
 mod error;
 mod fetch;
 mod handling;
-mod item_identity;
 mod models;
-mod parse;
+mod publish;
 mod schema;
 mod serve;
+#[cfg(test)]
+mod testing;
 
 use std::env;
+use std::error::Error as StdError;
+use std::fmt::Write;
+use std::fs;
+use std::io::{self, Read};
+use std::process;
+use std::time::Duration;
 
+use flate2::read::GzDecoder;
+use log::{LevelFilter, error};
 use tokio::runtime::Runtime;
 
+use feeds::{build, hash, item_identity, parse, sanitize};
+
 use crate::config::Feeds;
+use crate::fetch::Credentials;
+
+/// Maps the number of -v and -q flags to a log level,
+/// starting from showing progress along with warnings and errors.
+fn log_level(verbose: u8, quiet: u8) -> LevelFilter {
+    const LEVELS: [LevelFilter; 6] = [
+        LevelFilter::Off,
+        LevelFilter::Error,
+        LevelFilter::Warn,
+        LevelFilter::Info,
+        LevelFilter::Debug,
+        LevelFilter::Trace,
+    ];
+
+    let index = (3 + verbose as usize).saturating_sub(quiet as usize);
+    LEVELS[index.min(LEVELS.len() - 1)]
+}
+
+/// Reads a boolean setting from the environment, which is on when it's set
+/// to anything other than an empty string, 0, or false.
+fn env_flag(name: &str) -> bool {
+    env::var(name).is_ok_and(|value| is_truthy(&value))
+}
+
+fn is_truthy(value: &str) -> bool {
+    !matches!(value, "" | "0" | "false")
+}
+
+fn json_arg() -> clap::Arg {
+    clap::Arg::new("json")
+        .long("json")
+        .action(clap::ArgAction::SetTrue)
+        .help("Print a JSON summary for scripts")
+}
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Decompresses gzipped bytes, detected by the path's extension
+/// or the gzip magic number, and returns anything else as-is.
+fn decompress(path: &str, bytes: Vec<u8>) -> io::Result<Vec<u8>> {
+    if !path.ends_with(".gz") && !bytes.starts_with(&GZIP_MAGIC) {
+        return Ok(bytes);
+    }
+
+    let mut decompressed = Vec::new();
+    GzDecoder::new(&bytes[..]).read_to_end(&mut decompressed)?;
+    Ok(decompressed)
+}
+
+/// Reads a file, or stdin for -, decompressing it if it's gzipped.
+fn read_file(path: &str) -> io::Result<Vec<u8>> {
+    let bytes = if path == "-" {
+        let mut bytes = Vec::new();
+        io::stdin().read_to_end(&mut bytes)
+            .map(|_| bytes)
+    } else {
+        fs::read(path)
+    }?;
+    decompress(path, bytes)
+}
+
+/// Reads a newline-delimited list of feed urls, which may be gzipped.
+fn read_feed_list(path: &str) -> io::Result<String> {
+    String::from_utf8(read_file(path)?)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+/// Reads the password for a private feed from FEED_PASSWORD, or else a line
+/// of stdin, so it doesn't show up in the process list or shell history.
+fn read_password() -> io::Result<String> {
+    if let Ok(password) = env::var("FEED_PASSWORD") {
+        return Ok(password);
+    }
+    eprint!("Password: ");
+    let mut password = String::new();
+    io::stdin().read_line(&mut password)?;
+    Ok(password.trim_end_matches(&['\r', '\n'][..]).to_owned())
+}
+
+/// Parses a local feed file and summarizes it,
+/// for reproducing parsing bugs without fetching anything.
+fn summarize_feed_file(path: &str, options: &parse::ParseOptions)
+-> Result<String, Box<dyn StdError>> {
+    let source = read_file(path)?;
+    let (feed, warnings) = parse::Feed::parse_with_warnings(&source, options)?;
+
+    let mut summary = String::new();
+    writeln!(summary, "Title: {}", feed.title())?;
+    writeln!(summary, "Entries: {}", feed.len())?;
+    for warning in warnings.iter().chain(&feed.validate()) {
+        writeln!(summary, "Warning: {}", warning)?;
+    }
+    Ok(summary)
+}
 
 fn main() {
     let matches = clap::Command::new("feeds")
         .subcommand_required(true)
+        .arg(
+            clap::Arg::new("verbose")
+                .short('v')
+                .long("verbose")
+                .action(clap::ArgAction::Count)
+                .global(true)
+                .help("Log more details; may be repeated")
+        )
+        .arg(
+            clap::Arg::new("quiet")
+                .short('q')
+                .long("quiet")
+                .action(clap::ArgAction::Count)
+                .global(true)
+                .conflicts_with("verbose")
+                .help("Log less, hiding progress, then warnings, then errors; may be repeated")
+        )
         .subcommand(clap::Command::new("serve"))
-        .subcommand(clap::Command::new("fetch"))
+        .subcommand(
+            clap::Command::new("fetch")
+                .arg(
+                    clap::Arg::new("limit")
+                        .long("limit")
+                        .value_name("N")
+                        .value_parser(clap::value_parser!(usize))
+                        .help("Only fetch the first N feeds that are due")
+                )
+                .arg(json_arg())
+        )
+        .subcommand(clap::Command::new("reparse"))
+        .subcommand(
+            clap::Command::new("parse")
+                .arg(
+                    clap::Arg::new("PATH")
+                        .required(true)
+                        .help("A feed file to parse, which isn't saved")
+                )
+                .arg(
+                    clap::Arg::new("max-entries")
+                        .long("max-entries")
+                        .value_name("N")
+                        .value_parser(clap::value_parser!(usize))
+                        .help("Stop reading the feed after N entries")
+                )
+                .arg(
+                    clap::Arg::new("strict")
+                        .long("strict")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Reject malformed feeds instead of reading them leniently")
+                )
+        )
         .subcommand(
             clap::Command::new("subscribe")
                 .arg(
                     clap::Arg::new("FEED_URL")
+                        .required_unless_present("file")
+                )
+                .arg(
+                    clap::Arg::new("file")
+                        .long("file")
+                        .value_name("PATH")
+                        .conflicts_with("FEED_URL")
+                        .help("Subscribe to each url in a newline-delimited file, which may be gzipped, or - for stdin")
+                )
+                .arg(
+                    clap::Arg::new("backfill")
+                        .long("backfill")
+                        .value_name("PAGES")
+                        .value_parser(clap::value_parser!(usize))
+                        .help("Follow up to PAGES links to older entries of archived feeds")
+                )
+                .arg(
+                    clap::Arg::new("title")
+                        .long("title")
+                        .value_name("TITLE")
+                        .conflicts_with("file")
+                        .help("Use TITLE instead of the feed's own title")
+                )
+                .arg(
+                    clap::Arg::new("username")
+                        .long("username")
+                        .value_name("USER")
+                        .conflicts_with_all(["file", "token"])
+                        .help("Fetch a private feed with HTTP basic auth as USER, \
+                            reading the password from FEED_PASSWORD or stdin")
+                )
+                .arg(
+                    clap::Arg::new("token")
+                        .long("token")
+                        .value_name("TOKEN")
+                        .conflicts_with("file")
+                        .help("Fetch a private feed with TOKEN as a bearer token")
+                )
+                .arg(
+                    clap::Arg::new("group")
+                        .long("group")
+                        .value_name("GROUP")
+                        .help("Add feeds to GROUP, creating it if needed, instead of DEFAULT_GROUP")
+                )
+                .arg(json_arg())
+        )
+        .subcommand(
+            clap::Command::new("unsubscribe")
+                .arg(
+                    clap::Arg::new("FEED")
+                        .required(true)
+                        .help("The url or id of the feed to remove along with all its items")
+                )
+                .arg(
+                    clap::Arg::new("keep-saved")
+                        .long("keep-saved")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Keep the feed's saved items, disabling the feed instead of deleting it")
+                )
+        )
+        .subcommand(
+            clap::Command::new("backfill")
+                .arg(
+                    clap::Arg::new("FEED")
+                        .required(true)
+                        .help("The url or id of the feed to continue backfilling older entries of")
+                )
+                .arg(
+                    clap::Arg::new("pages")
+                        .long("pages")
+                        .value_name("PAGES")
+                        .value_parser(clap::value_parser!(usize))
+                        .default_value("10")
+                        .help("Follow up to PAGES more links to older entries")
+                )
+        )
+        .subcommand(
+            clap::Command::new("mark-read-on-fetch")
+                .arg(
+                    clap::Arg::new("FEED")
+                        .required(true)
+                        .help("The url or id of the feed whose new items should start out read")
+                )
+                .arg(
+                    clap::Arg::new("off")
+                        .long("off")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Stop marking the feed's new items read")
+                )
+        )
+        .subcommand(
+            clap::Command::new("mark-unread")
+                .arg(
+                    clap::Arg::new("TARGET")
+                        .required(true)
+                        .help("The url or id of the feed whose items to mark unread")
+                )
+                .arg(
+                    clap::Arg::new("group")
+                        .long("group")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Treat TARGET as the title or id of a group, marking all its feeds unread")
+                )
+                .arg(
+                    clap::Arg::new("skip-saved")
+                        .long("skip-saved")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Leave saved items as they are")
+                )
+        )
+        .subcommand(
+            clap::Command::new("fetch-log")
+                .arg(
+                    clap::Arg::new("FEED")
                         .required(true)
+                        .help("The url or id of the feed whose recent fetches to show")
+                )
+                .arg(
+                    clap::Arg::new("limit")
+                        .long("limit")
+                        .value_name("N")
+                        .value_parser(clap::value_parser!(i64).range(1..))
+                        .default_value("20")
+                        .help("Show the latest N fetches")
+                )
+                .arg(json_arg())
+        )
+        .subcommand(
+            clap::Command::new("prune")
+                .arg(
+                    clap::Arg::new("vacuum")
+                        .long("vacuum")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Reclaim disk space after pruning, which locks the item table")
+                )
+                .arg(json_arg())
+        )
+        .subcommand(
+            clap::Command::new("archive")
+                .arg(
+                    clap::Arg::new("days")
+                        .long("days")
+                        .value_name("DAYS")
+                        .value_parser(clap::value_parser!(i64))
+                        .default_value("365")
+                        .help("Archive saved items published more than DAYS ago")
                 )
         )
-        .subcommand(clap::Command::new("prune"))
         .get_matches();
 
-    env_logger::init();
+    // RUST_LOG still takes precedence over the verbosity flags when it's set
+    env_logger::Builder::new()
+        .filter_level(log_level(matches.get_count("verbose"), matches.get_count("quiet")))
+        .parse_default_env()
+        .init();
+
+    // Parsing a local file doesn't need any configuration
+    if let Some(("parse", parse_matches)) = matches.subcommand() {
+        let path = parse_matches.get_one::<String>("PATH")
+            .expect("PATH was not provided");
+        let options = parse::ParseOptions {
+            max_entries: parse_matches.get_one::<usize>("max-entries").copied(),
+            strict: parse_matches.get_flag("strict"),
+        };
+        match summarize_feed_file(path, &options) {
+            Ok(summary) => print!("{}", summary),
+            Err(err) => {
+                error!("Error parsing {}: {}", path, err);
+                process::exit(1);
+            }
+        }
+        return;
+    }
 
     let feeds = env::var("DATABASE_URL")
         .map(Feeds::new)
-        .expect("DATABASE_URL must be set");
+        .expect("DATABASE_URL must be set")
+        .with_default_group(env::var("DEFAULT_GROUP").ok())
+        .with_base_url(env::var("BASE_URL").ok())
+        .with_hub_url(env::var("WEBSUB_HUB_URL").ok())
+        .with_strip_params(env::var("STRIP_QUERY_PARAMS").map_or(Vec::new(), |params| {
+            params.split(',')
+                .map(str::trim)
+                .filter(|param| !param.is_empty())
+                .map(str::to_owned)
+                .collect()
+        }))
+        .with_fold_category_case(env_flag("FOLD_CATEGORY_CASE"))
+        .with_store_bodies(env_flag("STORE_FEED_BODIES"))
+        .with_sanitize_html(env_flag("SANITIZE_HTML"));
+    let feeds = match env::var("MAX_FEED_SIZE") {
+        Ok(size) => {
+            let size = size.parse().expect("MAX_FEED_SIZE must be a number of bytes");
+            feeds.with_max_feed_size(size)
+        }
+        Err(_) => feeds,
+    };
+    let feeds = match env::var("FETCH_INTERVAL") {
+        Ok(minutes) => {
+            let minutes: u64 = minutes.parse().expect("FETCH_INTERVAL must be a number of minutes");
+            feeds.with_fetch_interval(Duration::from_secs(minutes * 60))
+        }
+        Err(_) => feeds,
+    };
+    let feeds = match env::var("INSERT_BATCH_SIZE") {
+        Ok(size) => {
+            let size = size.parse().expect("INSERT_BATCH_SIZE must be a number of items");
+            feeds.with_insert_batch_size(size)
+        }
+        Err(_) => feeds,
+    };
+    let feeds = match env::var("DATABASE_POOL_SIZE") {
+        Ok(size) => {
+            let size = size.parse().expect("DATABASE_POOL_SIZE must be a number");
+            feeds.with_pool_size(size)
+        }
+        Err(_) => feeds,
+    };
 
     match matches.subcommand() {
         Some(("serve", _)) => {
@@ -54,22 +415,155 @@ fn main() {
                 .expect("Error creating runtime");
             let _ = rt.block_on(feeds.serve(port, creds));
         }
-        Some(("fetch", _)) => {
+        Some(("fetch", fetch_matches)) => {
             let rt = Runtime::new()
                 .expect("Error creating runtime");
-            let _ = rt.block_on(feeds.fetch());
+            let feeds = match fetch_matches.get_one::<usize>("limit") {
+                Some(&limit) => feeds.with_fetch_limit(limit),
+                None => feeds,
+            };
+            let _ = rt.block_on(feeds.fetch(fetch_matches.get_flag("json")));
+        }
+        Some(("reparse", _)) => {
+            feeds.reparse();
         }
         Some(("subscribe", subscribe_matches)) => {
-            let url = subscribe_matches.get_one::<String>("FEED_URL")
-                .expect("FEED_URL was not provided");
             let rt = Runtime::new()
                 .expect("Error creating runtime");
-            let _ = rt.block_on(feeds.subscribe(url));
+            let json = subscribe_matches.get_flag("json");
+            let feeds = match subscribe_matches.get_one::<usize>("backfill") {
+                Some(&pages) => feeds.with_backfill_pages(pages),
+                None => feeds,
+            };
+            let feeds = match subscribe_matches.get_one::<String>("group") {
+                Some(group) => feeds.with_default_group(Some(group.clone())),
+                None => feeds,
+            };
+            if let Some(path) = subscribe_matches.get_one::<String>("file") {
+                let list = read_feed_list(path)
+                    .expect("Error reading feed list");
+                rt.block_on(feeds.subscribe_all(&list, json));
+            } else {
+                let url = subscribe_matches.get_one::<String>("FEED_URL")
+                    .expect("FEED_URL was not provided");
+                let title = subscribe_matches.get_one::<String>("title")
+                    .map(String::as_str);
+                let username = subscribe_matches.get_one::<String>("username");
+                let token = subscribe_matches.get_one::<String>("token");
+                let password = username.map(|_| read_password()
+                    .expect("Error reading password"));
+                let credentials = match (username, token) {
+                    (Some(username), _) => Some(Credentials::Basic {
+                        username,
+                        password: password.as_deref().filter(|password| !password.is_empty()),
+                    }),
+                    (None, Some(token)) => Some(Credentials::Bearer(token)),
+                    (None, None) => None,
+                };
+                rt.block_on(feeds.subscribe(url, credentials, title, json));
+            }
+        }
+        Some(("unsubscribe", unsubscribe_matches)) => {
+            let feed = unsubscribe_matches.get_one::<String>("FEED")
+                .expect("FEED was not provided");
+            feeds.unsubscribe(feed, unsubscribe_matches.get_flag("keep-saved"));
+        }
+        Some(("backfill", backfill_matches)) => {
+            let feed = backfill_matches.get_one::<String>("FEED")
+                .expect("FEED was not provided");
+            let pages = *backfill_matches.get_one::<usize>("pages")
+                .expect("pages has a default");
+            let rt = Runtime::new()
+                .expect("Error creating runtime");
+            rt.block_on(feeds.with_backfill_pages(pages).backfill(feed));
+        }
+        Some(("mark-read-on-fetch", mark_matches)) => {
+            let feed = mark_matches.get_one::<String>("FEED")
+                .expect("FEED was not provided");
+            feeds.mark_read_on_fetch(feed, !mark_matches.get_flag("off"));
+        }
+        Some(("mark-unread", unread_matches)) => {
+            let target = unread_matches.get_one::<String>("TARGET")
+                .expect("TARGET was not provided");
+            feeds.mark_unread(
+                target, unread_matches.get_flag("group"), unread_matches.get_flag("skip-saved"),
+            );
         }
-        Some(("prune", _)) => {
-            feeds.prune();
+        Some(("fetch-log", log_matches)) => {
+            let feed = log_matches.get_one::<String>("FEED")
+                .expect("FEED was not provided");
+            let limit = *log_matches.get_one::<i64>("limit")
+                .expect("limit has a default");
+            feeds.fetch_log(feed, limit, log_matches.get_flag("json"));
+        }
+        Some(("prune", prune_matches)) => {
+            feeds.prune(prune_matches.get_flag("vacuum"), prune_matches.get_flag("json"));
+        }
+        Some(("archive", archive_matches)) => {
+            let days = *archive_matches.get_one::<i64>("days")
+                .expect("days has a default");
+            feeds.archive(days);
         }
         _ => unreachable!(),
     }
 
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_level() {
+        assert_eq!(log_level(0, 0), LevelFilter::Info);
+        assert_eq!(log_level(1, 0), LevelFilter::Debug);
+        assert_eq!(log_level(2, 0), LevelFilter::Trace);
+        assert_eq!(log_level(9, 0), LevelFilter::Trace);
+        assert_eq!(log_level(0, 1), LevelFilter::Warn);
+        assert_eq!(log_level(0, 2), LevelFilter::Error);
+        assert_eq!(log_level(0, 3), LevelFilter::Off);
+        assert_eq!(log_level(0, 9), LevelFilter::Off);
+    }
+
+    #[test]
+    fn test_is_truthy() {
+        assert!(is_truthy("1"));
+        assert!(is_truthy("true"));
+        assert!(!is_truthy(""));
+        assert!(!is_truthy("0"));
+        assert!(!is_truthy("false"));
+    }
+
+    #[test]
+    fn test_summarize_feed_file() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/test-data/techcrunch.xml");
+        let summary = summarize_feed_file(path, &parse::ParseOptions::default()).unwrap();
+        let lines: Vec<_> = summary.lines().collect();
+        assert_eq!(lines[0], "Title: TechCrunch");
+        assert_eq!(lines[1], "Entries: 2");
+        assert_eq!(lines.len(), 3);
+        assert!(lines[2].starts_with("Warning: <link>: "));
+
+        let options = parse::ParseOptions { max_entries: Some(1), ..Default::default() };
+        let summary = summarize_feed_file(path, &options).unwrap();
+        assert_eq!(summary.lines().nth(1), Some("Entries: 1"));
+
+        assert!(summarize_feed_file("test-data/missing.xml", &parse::ParseOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_read_gzipped_feed_list() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/test-data/feeds.txt.gz");
+        let list = read_feed_list(path).unwrap();
+        let urls: Vec<_> = list.lines()
+            .filter(|line| !line.starts_with('#'))
+            .collect();
+        assert_eq!(urls, ["http://techcrunch.com/feed/", "http://www.theverge.com/rss/index.xml"]);
+
+        // Gzipped input is detected without the extension too, like from stdin
+        let gzipped = fs::read(path).unwrap();
+        assert_eq!(decompress("-", gzipped).unwrap(), list.as_bytes());
+        let plain = b"http://techcrunch.com/feed/\n".to_vec();
+        assert_eq!(decompress("feeds.txt", plain.clone()).unwrap(), plain);
+    }
+}
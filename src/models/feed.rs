@@ -1,3 +1,5 @@
+use chrono::NaiveDateTime;
+
 use crate::schema::feed;
 use super::group::Group;
 
@@ -10,6 +12,27 @@ pub struct Feed {
     pub title: String,
     pub group_id: Option<i32>,
     pub site_url: Option<String>,
+    pub is_disabled: bool,
+    pub subtitle: Option<String>,
+    pub has_custom_title: bool,
+    pub auth_username: Option<String>,
+    /// Stored in plaintext since it has to be sent with each fetch,
+    /// so it must never be printed or included in JSON output.
+    pub auth_password: Option<String>,
+    /// Stored in plaintext like `auth_password`.
+    pub auth_token: Option<String>,
+    pub next_fetch: Option<NaiveDateTime>,
+    /// Items published before this were marked read along with the feed.
+    pub read_before: Option<NaiveDateTime>,
+    /// Problems found the last time this feed was parsed, one per line.
+    pub last_warnings: Option<String>,
+    /// Whether new items start out read, for noisy feeds like status pages.
+    pub mark_read_on_fetch: bool,
+    /// The fingerprint of the feed when its entries were last ingested.
+    pub fingerprint: Option<i64>,
+    /// The next archive page to backfill older items from,
+    /// if backfilling stopped before reaching the oldest page.
+    pub backfill_url: Option<String>,
 }
 
 #[derive(Insertable)]
@@ -18,4 +41,10 @@ pub struct NewFeed<'a> {
     pub url: &'a str,
     pub title: &'a str,
     pub site_url: Option<&'a str>,
+    pub group_id: Option<i32>,
+    pub subtitle: Option<&'a str>,
+    pub has_custom_title: bool,
+    pub auth_username: Option<&'a str>,
+    pub auth_password: Option<&'a str>,
+    pub auth_token: Option<&'a str>,
 }
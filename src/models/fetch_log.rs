@@ -0,0 +1,27 @@
+use chrono::NaiveDateTime;
+
+use crate::schema::fetch_log;
+use super::feed::Feed;
+
+#[derive(Identifiable, Queryable, Associations)]
+#[diesel(belongs_to(Feed))]
+#[diesel(table_name = fetch_log)]
+pub struct FetchLog {
+    pub id: i32,
+    pub feed_id: i32,
+    pub fetched: NaiveDateTime,
+    pub status: String,
+    pub http_code: Option<i32>,
+    pub item_count: i32,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Insertable, PartialEq)]
+#[diesel(table_name = fetch_log)]
+pub struct NewFetchLog<'a> {
+    pub feed_id: i32,
+    pub status: &'a str,
+    pub http_code: Option<i32>,
+    pub item_count: i32,
+    pub error: Option<String>,
+}
@@ -6,3 +6,9 @@ pub struct Group {
     pub id: i32,
     pub title: String,
 }
+
+#[derive(Insertable)]
+#[diesel(table_name = feed_group)]
+pub struct NewGroup<'a> {
+    pub title: &'a str,
+}
@@ -1,6 +1,6 @@
 use chrono::NaiveDateTime;
 
-use crate::schema::item;
+use crate::schema::{item, item_category};
 use super::feed::Feed;
 
 #[derive(Identifiable, Queryable, Associations)]
@@ -18,6 +18,8 @@ pub struct Item {
     pub author: Option<String>,
     pub fetched: NaiveDateTime,
     pub guid: Option<String>,
+    pub summary: Option<String>,
+    pub updated: Option<NaiveDateTime>,
 }
 
 #[derive(Insertable)]
@@ -30,4 +32,15 @@ pub struct NewItem<'a> {
     pub feed_id: i32,
     pub author: Option<&'a str>,
     pub guid: Option<&'a str>,
+    pub summary: Option<&'a str>,
+    pub is_read: bool,
+    pub updated: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = item_category)]
+pub struct NewItemCategory<'a> {
+    pub item_id: i32,
+    pub term: &'a str,
+    pub scheme: Option<&'a str>,
 }
@@ -1,3 +1,4 @@
 pub mod feed;
+pub mod fetch_log;
 pub mod group;
 pub mod item;
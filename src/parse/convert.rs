@@ -0,0 +1,175 @@
+use atom_syndication as atom;
+use chrono::{DateTime, FixedOffset, Utc};
+
+use crate::item_identity::synthesize_id;
+use super::entry::Entry;
+use super::feed::Feed;
+
+fn link(href: String, rel: &str) -> atom::Link {
+    atom::Link { href, rel: rel.to_owned(), ..Default::default() }
+}
+
+fn html_text(value: String) -> atom::Text {
+    atom::Text { value, r#type: atom::TextType::Html, ..Default::default() }
+}
+
+fn entry_with_default_date(entry: Entry, default_date: DateTime<FixedOffset>) -> atom::Entry {
+    // Atom requires every entry to have an id, so one is made up if needed
+    let id = entry.guid.clone()
+        .or_else(|| entry.link.clone())
+        .unwrap_or_else(|| synthesize_id(&[&entry.title, &entry.content]));
+    let updated = entry.updated.or(entry.published).unwrap_or(default_date);
+
+    let mut links: Vec<_> = entry.link.into_iter()
+        .map(|href| link(href, "alternate"))
+        .collect();
+    links.extend(entry.comments_url.map(|href| link(href, "replies")));
+    links.extend(entry.enclosures);
+
+    let entry_content = entry.content;
+    let summary = Some(entry.summary)
+        .filter(|summary| !summary.is_empty() && *summary != entry_content)
+        .map(html_text);
+    let content = Some(entry_content)
+        .filter(|content| !content.is_empty())
+        .map(|content| atom::Content {
+            value: Some(content),
+            content_type: Some("html".to_owned()),
+            ..Default::default()
+        });
+
+    atom::Entry {
+        title: entry.title.into(),
+        id,
+        updated,
+        published: entry.published,
+        authors: entry.author.into_iter()
+            .map(|name| atom::Person { name, ..Default::default() })
+            .collect(),
+        categories: entry.categories.into_iter()
+            .map(|category| atom::Category {
+                term: category.term,
+                scheme: category.scheme,
+                label: None,
+            })
+            .collect(),
+        links,
+        summary,
+        content,
+        ..Default::default()
+    }
+}
+
+impl From<Entry> for atom::Entry {
+    /// Converts an entry into Atom, using its link as the id if it has no guid
+    /// and dating it now if it has no date.
+    fn from(entry: Entry) -> Self {
+        entry_with_default_date(entry, Utc::now().into())
+    }
+}
+
+impl From<Feed> for atom::Feed {
+    /// Converts a feed into Atom, leaving Atom feeds as they were parsed.
+    /// Undated RSS items get the date of the latest activity in the feed,
+    /// and items without an author inherit the feed's title as one.
+    fn from(feed: Feed) -> Self {
+        let channel = match feed {
+            Feed::Atom(feed) => return feed,
+            Feed::Rss(_) => &feed,
+        };
+
+        let updated = channel.latest_updated().unwrap_or_else(|| Utc::now().into());
+        let title = channel.title().to_owned();
+        let subtitle = channel.subtitle().map(|subtitle| subtitle.to_owned().into());
+        let site_url = channel.site_url()
+            .filter(|url| !url.is_empty())
+            .map(str::to_owned);
+        let id = site_url.clone().unwrap_or_else(|| synthesize_id(&[&title]));
+
+        let entries: Vec<_> = feed.into_iter()
+            .map(|entry| entry_with_default_date(entry, updated))
+            .collect();
+        // Atom requires an author for every entry, which they can inherit
+        let authors = if entries.iter().any(|entry| entry.authors.is_empty()) {
+            vec![atom::Person { name: title.clone(), ..Default::default() }]
+        } else {
+            Vec::new()
+        };
+
+        atom::Feed {
+            title: title.into(),
+            id,
+            updated,
+            authors,
+            subtitle,
+            links: site_url.into_iter().map(|href| link(href, "alternate")).collect(),
+            entries,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use atom_syndication as atom;
+
+    use crate::parse::{Feed, LinkExt};
+
+    static RSS_STR: &str = r#"
+<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0">
+  <channel>
+    <title>TechCrunch</title>
+    <link>http://techcrunch.com</link>
+    <description>The latest technology news and information on startups</description>
+    <lastBuildDate>Tue, 02 Apr 2019 07:30:00 GMT</lastBuildDate>
+    <item>
+      <title>Ford hires Elon Musk as CEO</title>
+      <link>http://techcrunch.com/2019/04/01/ford-elon-musk/</link>
+      <pubDate>Mon, 01 Apr 2019 07:30:00 GMT</pubDate>
+      <author>Matthew Lynley</author>
+      <category>Cars</category>
+      <description>&lt;p&gt;In an unprecedented move, Ford hires Elon Musk.&lt;/p&gt;</description>
+    </item>
+    <item>
+      <title>Elon Musk leaves Ford</title>
+      <description>Elon Musk has left Ford.</description>
+    </item>
+  </channel>
+</rss>
+"#;
+
+    #[test]
+    fn test_rss_to_atom() {
+        let feed = Feed::parse(RSS_STR.as_bytes()).unwrap();
+        let atom_feed = atom::Feed::from(feed);
+
+        assert_eq!(atom_feed.title().as_str(), "TechCrunch");
+        assert_eq!(atom_feed.id(), "http://techcrunch.com");
+        assert_eq!(atom_feed.updated().to_rfc3339(), "2019-04-02T07:30:00+00:00");
+        assert!(atom_feed.links()[0].rel_is("alternate"));
+
+        let entry = &atom_feed.entries()[0];
+        assert_eq!(entry.id(), "http://techcrunch.com/2019/04/01/ford-elon-musk/");
+        assert_eq!(entry.updated().to_rfc3339(), "2019-04-01T07:30:00+00:00");
+        assert_eq!(entry.authors()[0].name(), "Matthew Lynley");
+        assert_eq!(entry.categories()[0].term(), "Cars");
+        assert!(entry.summary().is_none());
+
+        // Undated entries without ids get them synthesized
+        let entry = &atom_feed.entries()[1];
+        assert!(entry.id().starts_with("urn:feeds:md5:"));
+        assert_eq!(entry.updated(), atom_feed.updated());
+        assert_eq!(atom_feed.authors()[0].name(), "TechCrunch");
+
+        // The output can be parsed again as the same feed
+        let xml = atom_feed.to_string();
+        let reparsed = Feed::parse(xml.as_bytes()).unwrap();
+        assert_eq!(reparsed.validate_atom(), []);
+        let entries: Vec<_> = reparsed.entries().collect();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].content, "<p>In an unprecedented move, Ford hires Elon Musk.</p>");
+        assert_eq!(entries[0].link.as_deref(), Some("http://techcrunch.com/2019/04/01/ford-elon-musk/"));
+        assert_eq!(entries[1].title, "Elon Musk leaves Ford");
+    }
+}
@@ -0,0 +1,95 @@
+use std::borrow::Cow;
+
+fn named_entity(name: &str) -> Option<char> {
+    let c = match name {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        // Punctuation that can hide the scheme of a url
+        "colon" => ':',
+        "sol" => '/',
+        "quest" => '?',
+        "num" => '#',
+        "Tab" => '\t',
+        "NewLine" => '\n',
+        "nbsp" => '\u{a0}',
+        "ndash" => '\u{2013}',
+        "mdash" => '\u{2014}',
+        "lsquo" => '\u{2018}',
+        "rsquo" => '\u{2019}',
+        "ldquo" => '\u{201c}',
+        "rdquo" => '\u{201d}',
+        "hellip" => '\u{2026}',
+        "copy" => '\u{a9}',
+        "reg" => '\u{ae}',
+        "trade" => '\u{2122}',
+        _ => return None,
+    };
+    Some(c)
+}
+
+fn decode_entity(entity: &str) -> Option<char> {
+    match entity.strip_prefix('#') {
+        Some(num) => {
+            let code = match num.strip_prefix('x').or_else(|| num.strip_prefix('X')) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => num.parse().ok()?,
+            };
+            char::from_u32(code)
+        }
+        None => named_entity(entity),
+    }
+}
+
+/// Decodes the HTML character references left in text after XML parsing,
+/// like the `&mdash;` of a title escaped as `&amp;mdash;`.
+/// Text is only decoded once, and unknown references are kept as-is.
+pub fn decode_entities(text: &str) -> Cow<'_, str> {
+    if !text.contains('&') {
+        return Cow::Borrowed(text);
+    }
+
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let entity = rest[1..].find(';')
+            .map(|end| &rest[1..end + 1])
+            .filter(|entity| entity.len() <= 10)
+            .and_then(|entity| decode_entity(entity).map(|c| (entity, c)));
+        match entity {
+            Some((entity, c)) => {
+                decoded.push(c);
+                // Skip past the & and ;
+                rest = &rest[entity.len() + 2..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    Cow::Owned(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+    use super::decode_entities;
+
+    #[test]
+    fn test_decode_entities() {
+        assert!(matches!(decode_entities("Ford hires Elon Musk"), Cow::Borrowed(_)));
+        assert_eq!(decode_entities("Ford &mdash; Tesla"), "Ford \u{2014} Tesla");
+        assert_eq!(decode_entities("Ford &#8212; Tesla &#x2014;"), "Ford \u{2014} Tesla \u{2014}");
+        assert_eq!(decode_entities("AT&amp;T"), "AT&T");
+        // Only decoded once
+        assert_eq!(decode_entities("&amp;mdash;"), "&mdash;");
+        assert_eq!(decode_entities("AT&T & Verizon &bogus; &"), "AT&T & Verizon &bogus; &");
+    }
+}
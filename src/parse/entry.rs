@@ -1,44 +1,370 @@
-use atom_syndication::{Entry as AtomEntry};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashSet};
+use std::mem;
+use std::time::Duration;
+
+use atom_syndication::{Entry as AtomEntry, Feed as AtomFeed, Link, Person, TextType};
+use atom_syndication::extension::Extension as AtomExtension;
 use chrono::{DateTime, FixedOffset};
 use rss::{Item as RssItem};
+use rss::extension::Extension as RssExtension;
 use url::Url;
 
 use crate::item_identity::ItemIdentifier;
+use super::entities::decode_entities;
+use super::link::{LinkExt, dedup_links, find_alternate, links_of_type, related_links, via_link};
+
+/// Parses a date that should be RFC 2822, but may be RFC 3339
+/// or have the wrong day of the week.
+pub(super) fn parse_date(date: &str) -> Option<DateTime<FixedOffset>> {
+    let date = date.trim();
+    DateTime::parse_from_rfc2822(date)
+        .or_else(|_| DateTime::parse_from_rfc3339(date))
+        .ok()
+        .or_else(|| {
+            let (_, date) = date.split_once(',')?;
+            DateTime::parse_from_rfc2822(date.trim()).ok()
+        })
+}
+
+/// Returns the authors of an Atom entry, inheriting them from its source
+/// or the feed containing it when it has none, per RFC 4287.
+pub fn effective_authors<'a>(entry: &'a AtomEntry, feed: Option<&'a AtomFeed>)
+-> &'a [Person] {
+    let source_authors = entry.source().map_or(&[][..], |source| source.authors());
+    let feed_authors = feed.map_or(&[][..], |feed| feed.authors());
+
+    [entry.authors(), source_authors, feed_authors].iter()
+        .copied()
+        .find(|authors| !authors.is_empty())
+        .unwrap_or(&[])
+}
+
+/// The parts of RSS and Atom extension elements needed to find thumbnails.
+trait MediaElement: Sized {
+    fn attr(&self, name: &str) -> Option<&str>;
+
+    fn child_elements(&self, name: &str) -> &[Self];
+}
+
+impl MediaElement for RssExtension {
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs().get(name).map(String::as_str)
+    }
+
+    fn child_elements(&self, name: &str) -> &[Self] {
+        self.children().get(name).map_or(&[], Vec::as_slice)
+    }
+}
+
+impl MediaElement for AtomExtension {
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs().get(name).map(String::as_str)
+    }
+
+    fn child_elements(&self, name: &str) -> &[Self] {
+        self.children().get(name).map_or(&[], Vec::as_slice)
+    }
+}
+
+/// Returns the Media RSS elements with the given name,
+/// followed by those inside a media:group.
+fn media_elements<'a, E: MediaElement>(media: &'a BTreeMap<String, Vec<E>>, name: &'a str)
+-> impl Iterator<Item=&'a E> {
+    let groups = media.get("group").map_or(&[][..], Vec::as_slice);
+    media.get(name).map_or(&[][..], Vec::as_slice).iter()
+        .chain(groups.iter().flat_map(move |group| group.child_elements(name)))
+}
+
+/// Finds a thumbnail among Media RSS elements, preferring media:thumbnail
+/// over media:content that is an image.
+fn media_thumbnail<E: MediaElement>(media: &BTreeMap<String, Vec<E>>) -> Option<&str> {
+    let is_image = |content: &&E| {
+        content.attr("medium") == Some("image")
+            || content.attr("type").is_some_and(|t| t.starts_with("image/"))
+    };
+
+    media_elements(media, "thumbnail")
+        .find_map(|thumbnail| thumbnail.attr("url"))
+        .or_else(|| {
+            media_elements(media, "content")
+                .filter(is_image)
+                .find_map(|content| content.attr("url"))
+        })
+}
+
+/// Replaces each html tag with a space, leaving only the text between them.
+fn strip_tags(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => {
+                in_tag = false;
+                text.push(' ');
+            }
+            c if !in_tag => text.push(c),
+            _ => (),
+        }
+    }
+    text
+}
+
+/// Trims whitespace from a string in place, without copying it.
+fn trimmed(mut text: String) -> String {
+    text.truncate(text.trim_end().len());
+    let start = text.len() - text.trim_start().len();
+    text.drain(..start);
+    text
+}
+
+/// Picks the content and summary of an entry the way `EntryRef::content` and
+/// `EntryRef::summary` do, each falling back to the other, from owned text.
+fn content_and_summary(content: Option<String>, summary: Option<String>) -> (String, String) {
+    match (content, summary) {
+        (Some(content), Some(summary)) => (trimmed(content), trimmed(summary)),
+        (Some(text), None) | (None, Some(text)) => {
+            let text = trimmed(text);
+            (text.clone(), text)
+        }
+        (None, None) => (String::new(), String::new()),
+    }
+}
+
+/// Converts html to text on a single line, with whitespace collapsed.
+fn plain_text(html: &str) -> String {
+    let text = strip_tags(html);
+    decode_entities(&text).split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Returns the summary html as plain text, falling back to the content html,
+/// cut off with an ellipsis if it's longer than max_chars.
+/// See `Entry::summary_text`.
+pub fn preview_text(summary: &str, content: &str, max_chars: Option<usize>) -> Option<String> {
+    let mut text = plain_text(summary);
+    if text.is_empty() {
+        text = plain_text(content);
+    }
+    if text.is_empty() {
+        return None;
+    }
+
+    match max_chars {
+        Some(max_chars) if text.chars().count() > max_chars => {
+            let mut truncated: String = text.chars().take(max_chars.saturating_sub(1)).collect();
+            truncated.truncate(truncated.trim_end().len());
+            truncated.push('…');
+            Some(truncated)
+        }
+        _ => Some(text),
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Category {
+    pub term: String,
+    pub scheme: Option<String>,
+}
 
 pub struct Entry {
     pub title: String,
     pub content: String,
+    pub summary: String,
     pub link: Option<String>,
     pub published: Option<DateTime<FixedOffset>>,
+    /// When the entry was last changed, which is the same as when it was
+    /// published for RSS.
+    pub updated: Option<DateTime<FixedOffset>>,
     pub author: Option<String>,
     pub guid: Option<String>,
+    /// Whether the guid is also the url of the entry, per RSS's isPermaLink.
+    pub guid_is_permalink: bool,
+    pub categories: Vec<Category>,
+    pub comments_url: Option<String>,
+    pub comments_count: Option<u32>,
+    pub thumbnail: Option<String>,
+    /// Attached media, like podcast audio, as Atom enclosure links.
+    pub enclosures: Vec<Link>,
+    /// All of the entry's links with their rel, type, hreflang, and title,
+    /// as far as the feed's format has them.
+    pub links: Vec<Link>,
 }
 
 impl Entry {
     pub fn from_ref(entry_ref: EntryRef) -> Entry {
         Entry {
-            title: entry_ref.title().to_owned(),
+            title: entry_ref.title().into_owned(),
             content: entry_ref.content().to_owned(),
+            summary: entry_ref.summary().to_owned(),
+            links: entry_ref.links(),
+            ..Entry::metadata_from_ref(entry_ref)
+        }
+    }
+
+    /// Converts an RSS item like `from_ref`, but moves its text into the entry
+    /// rather than copying it.
+    pub fn from_rss(mut item: RssItem) -> Entry {
+        let entry = Entry::metadata_from_ref(EntryRef::Rss(&item));
+        let links = EntryRef::Rss(&item).links();
+        let title = match EntryRef::Rss(&item).title() {
+            Cow::Owned(title) => Some(title),
+            Cow::Borrowed(_) => None,
+        }.unwrap_or_else(|| trimmed(item.title.take().unwrap_or_default()));
+        let (content, summary) = content_and_summary(item.content.take(), item.description.take());
+        Entry { title, content, summary, links, ..entry }
+    }
+
+    /// Converts an Atom entry like `from_ref`, but moves its text and links
+    /// into the entry rather than copying them.
+    pub fn from_atom(mut entry: AtomEntry, feed: &AtomFeed) -> Entry {
+        let metadata = Entry::metadata_from_ref(EntryRef::Atom(&entry, feed));
+        let title = match EntryRef::Atom(&entry, feed).title() {
+            Cow::Owned(title) => Some(title),
+            Cow::Borrowed(_) => None,
+        }.unwrap_or_else(|| trimmed(mem::take(&mut entry.title.value)));
+        let (content, summary) = content_and_summary(
+            entry.content.take().and_then(|content| content.value),
+            entry.summary.take().map(|summary| summary.value),
+        );
+        Entry { title, content, summary, links: entry.links, ..metadata }
+    }
+
+    /// Converts everything but the text and links of an entry,
+    /// which are left empty for the caller to fill in.
+    fn metadata_from_ref(entry_ref: EntryRef) -> Entry {
+        Entry {
+            title: String::new(),
+            content: String::new(),
+            summary: String::new(),
             link: entry_ref.link().map(str::to_owned),
             published: entry_ref.published(),
+            updated: entry_ref.updated(),
             author: entry_ref.author().map(str::to_owned),
             guid: entry_ref.guid().map(str::to_owned),
+            guid_is_permalink: entry_ref.guid_is_permalink(),
+            categories: entry_ref.categories(),
+            comments_url: entry_ref.comments_url().map(str::to_owned),
+            comments_count: entry_ref.comments_count(),
+            thumbnail: entry_ref.thumbnail().map(str::to_owned),
+            enclosures: entry_ref.enclosures(),
+            links: Vec::new(),
         }
     }
 
+    /// Counts the words of this entry's content, ignoring any html
+    /// and punctuation on its own, like a dash between words.
+    pub fn word_count(&self) -> usize {
+        let text = strip_tags(&self.content);
+        decode_entities(&text).split_whitespace()
+            .filter(|word| word.chars().any(char::is_alphanumeric))
+            .count()
+    }
+
+    /// Returns this entry's summary as plain text, falling back to its content,
+    /// with html removed and whitespace collapsed, for previews and
+    /// notifications. Text longer than max_chars is cut off with an ellipsis
+    /// so that it's max_chars long, or None is returned if there's no text.
+    pub fn summary_text(&self, max_chars: Option<usize>) -> Option<String> {
+        preview_text(&self.summary, &self.content, max_chars)
+    }
+
+    /// Estimates how long this entry takes to read at the given words per
+    /// minute, or None if it has no content to read.
+    pub fn reading_time(&self, wpm: u32) -> Option<Duration> {
+        let words = self.word_count() as u64;
+        if words == 0 || wpm == 0 {
+            return None;
+        }
+        Some(Duration::from_millis(words * 60_000 / u64::from(wpm)))
+    }
+
+    /// Removes enclosures repeating an earlier one's href, rel, and type.
+    pub fn dedup_links(&mut self) {
+        dedup_links(&mut self.enclosures);
+        dedup_links(&mut self.links);
+    }
+
+    /// Returns the enclosures whose media type starts with the given prefix.
+    pub fn links_of_type<'a>(&'a self, mime_prefix: &'a str) -> impl Iterator<Item=&'a Link> {
+        links_of_type(&self.enclosures, mime_prefix)
+    }
+
+    /// Returns the link to the source of this entry's information, if any.
+    pub fn via_link(&self) -> Option<&Link> {
+        via_link(&self.links)
+    }
+
+    /// Returns the links to resources related to this entry.
+    pub fn related_links(&self) -> impl Iterator<Item=&Link> {
+        related_links(&self.links)
+    }
+
+    /// Trims category terms and drops repeats that only differ in case,
+    /// keeping the first spelling, or lowercasing every term if fold_case.
+    pub fn normalize_categories(&mut self, fold_case: bool) {
+        let mut seen = HashSet::new();
+        self.categories.retain_mut(|category| {
+            let term = category.term.trim();
+            if fold_case {
+                category.term = term.to_lowercase();
+            } else if term.len() != category.term.len() {
+                category.term = term.to_owned();
+            }
+            !category.term.is_empty() && seen.insert(category.term.to_lowercase())
+        });
+    }
+
     pub fn clear_redundant_guid(&mut self) {
         if self.guid == self.link {
             self.guid = None;
+            self.guid_is_permalink = false;
         }
     }
 
-    pub(crate) fn expand_link(&mut self, base_url: &Url) {
-        let link_url = self.link.as_ref()
-            .and_then(|link| base_url.join(link).ok());
+    /// Resolves the link of this entry, which may be relative, against a base url.
+    pub fn resolve_link(&self, base_url: &Url) -> Option<Url> {
+        self.link.as_ref()
+            .and_then(|link| base_url.join(link).ok())
+    }
+
+    /// Replaces a relative link of this entry with one resolved against
+    /// the feed's base url.
+    pub fn expand_link(&mut self, base_url: &Url) {
+        let link_url = self.resolve_link(base_url);
 
         self.link = link_url.map(Into::into).or(self.link.take());
     }
 
+    /// Removes query parameters, like utm_source, from the link of this entry.
+    /// A param ending in * matches any parameter name with that prefix.
+    pub fn strip_query_params<S: AsRef<str>>(&mut self, params: &[S]) {
+        let mut link_url = match self.link.as_deref().map(Url::parse) {
+            Some(Ok(link_url)) => link_url,
+            _ => return,
+        };
+
+        let is_stripped = |name: &str| params.iter().any(|param| {
+            match param.as_ref().strip_suffix('*') {
+                Some(prefix) => name.starts_with(prefix),
+                None => name == param.as_ref(),
+            }
+        });
+        let (stripped, kept): (Vec<_>, Vec<_>) = link_url.query_pairs()
+            .map(|(name, value)| (name.into_owned(), value.into_owned()))
+            .partition(|(name, _)| is_stripped(name));
+        if stripped.is_empty() {
+            return;
+        }
+
+        if kept.is_empty() {
+            link_url.set_query(None);
+        } else {
+            link_url.query_pairs_mut().clear().extend_pairs(kept);
+        }
+        self.link = Some(link_url.into());
+    }
+
     pub fn identifier(&self) -> Option<ItemIdentifier> {
         ItemIdentifier::new(self.link.as_deref(), self.guid.as_deref())
     }
@@ -47,15 +373,25 @@ impl Entry {
 #[derive(Clone, Copy, Debug)]
 pub enum EntryRef<'a> {
     Rss(&'a RssItem),
-    Atom(&'a AtomEntry),
+    Atom(&'a AtomEntry, &'a AtomFeed),
 }
 
 impl<'a> EntryRef<'a> {
-    pub fn title(self) -> &'a str {
+    /// Returns the title of this entry, decoding any HTML character references
+    /// that were escaped within it, so it's only copied if it had any.
+    /// Atom titles are only decoded when their type says they're HTML,
+    /// since a text title means exactly what it says.
+    pub fn title(self) -> Cow<'a, str> {
         match self {
-            Self::Rss(item) => item.title().unwrap_or(""),
-            Self::Atom(entry) => entry.title(),
-        }.trim()
+            Self::Rss(item) => decode_entities(item.title().unwrap_or("").trim()),
+            Self::Atom(entry, _) => {
+                let title = entry.title();
+                match title.r#type {
+                    TextType::Html => decode_entities(title.trim()),
+                    TextType::Text | TextType::Xhtml => Cow::Borrowed(title.trim()),
+                }
+            }
+        }
     }
 
     pub fn content(self) -> &'a str {
@@ -64,7 +400,7 @@ impl<'a> EntryRef<'a> {
                 item.content()
                     .or(item.description())
             }
-            Self::Atom(entry) => {
+            Self::Atom(entry, _) => {
                 entry.content()
                     .and_then(|content| content.value())
                     .or(entry.summary().map(|summary| summary.as_str()))
@@ -72,6 +408,20 @@ impl<'a> EntryRef<'a> {
         }.unwrap_or("").trim()
     }
 
+    pub fn summary(self) -> &'a str {
+        match self {
+            Self::Rss(item) => {
+                item.description()
+                    .or(item.content())
+            }
+            Self::Atom(entry, _) => {
+                entry.summary()
+                    .map(|summary| summary.as_str())
+                    .or(entry.content().and_then(|content| content.value()))
+            }
+        }.unwrap_or("").trim()
+    }
+
     pub fn link(self) -> Option<&'a str> {
         match self {
             Self::Rss(item) => {
@@ -82,16 +432,12 @@ impl<'a> EntryRef<'a> {
                     .and_then(|ext| ext.value())
                     .or(item.link())
                     .or_else(|| {
-                        item.guid().and_then(|id| {
-                            if id.is_permalink() { Some(id.value()) }
-                            else { None }
-                        })
+                        if self.guid_is_permalink() { self.guid() }
+                        else { None }
                     })
             }
-            Self::Atom(entry) => {
-                entry.links()
-                    .iter().filter(|link| link.rel() == "alternate").next()
-                    .or(entry.links().first())
+            Self::Atom(entry, _) => {
+                find_alternate(entry.links())
                     .map(|link| link.href())
             }
         }
@@ -100,15 +446,28 @@ impl<'a> EntryRef<'a> {
     pub fn published(self) -> Option<DateTime<FixedOffset>> {
         match self {
             Self::Rss(item) => {
+                // The DC date is also used when pubDate can't be parsed
                 item.pub_date()
-                    .and_then(|s| DateTime::parse_from_rfc2822(s).ok())
+                    .and_then(parse_date)
+                    .or_else(|| {
+                        item.dublin_core_ext()
+                            .and_then(|ext| ext.dates().first())
+                            .and_then(|date| parse_date(date))
+                    })
             }
-            Self::Atom(entry) => {
+            Self::Atom(entry, _) => {
                 Some(*entry.published().unwrap_or(entry.updated()))
             }
         }
     }
 
+    pub fn updated(self) -> Option<DateTime<FixedOffset>> {
+        match self {
+            Self::Rss(_) => self.published(),
+            Self::Atom(entry, _) => Some(*entry.updated()),
+        }
+    }
+
     pub fn author(self) -> Option<&'a str> {
         match self {
             Self::Rss(item) => {
@@ -119,18 +478,170 @@ impl<'a> EntryRef<'a> {
                             .map(String::as_str)
                     })
             }
-            Self::Atom(entry) => {
-                entry.authors()
+            Self::Atom(entry, feed) => {
+                effective_authors(entry, Some(feed))
                     .first()
                     .map(|author| author.name())
             }
         }
     }
 
+    pub fn comments_url(self) -> Option<&'a str> {
+        match self {
+            Self::Rss(item) => item.comments(),
+            Self::Atom(entry, _) => {
+                entry.links().iter()
+                    .find(|link| link.rel_is("replies"))
+                    .map(|link| link.href())
+            }
+        }
+    }
+
+    /// Returns the number of comments from the slash:comments element of RSS,
+    /// or the thr:total element of Atom, per RFC 4685.
+    pub fn comments_count(self) -> Option<u32> {
+        let count = match self {
+            Self::Rss(item) => {
+                item.extensions().get("slash")
+                    .and_then(|ext| ext.get("comments"))
+                    .and_then(|exts| exts.first())
+                    .and_then(|ext| ext.value())
+            }
+            Self::Atom(entry, _) => {
+                entry.extensions().get("thr")
+                    .and_then(|ext| ext.get("total"))
+                    .and_then(|exts| exts.first())
+                    .and_then(|ext| ext.value())
+            }
+        };
+        count.and_then(|count| count.trim().parse().ok())
+    }
+
+    /// Returns the url of an image for this entry, trying media:thumbnail,
+    /// then media:content with an image medium or type, then itunes:image.
+    pub fn thumbnail(self) -> Option<&'a str> {
+        let url = match self {
+            Self::Rss(item) => {
+                item.extensions().get("media")
+                    .and_then(media_thumbnail)
+                    .or_else(|| item.itunes_ext().and_then(|ext| ext.image()))
+            }
+            Self::Atom(entry, _) => {
+                let extensions = entry.extensions();
+                extensions.get("media")
+                    .and_then(media_thumbnail)
+                    .or_else(|| {
+                        extensions.get("itunes")
+                            .and_then(|ext| ext.get("image"))
+                            .and_then(|exts| exts.first())
+                            .and_then(|ext| ext.attr("href"))
+                    })
+            }
+        };
+        url.map(str::trim).filter(|url| !url.is_empty())
+    }
+
+    /// Returns every link of this entry. RSS items only have a link,
+    /// which is treated as the alternate, and their enclosure.
+    pub fn links(self) -> Vec<Link> {
+        match self {
+            Self::Rss(item) => {
+                let link = item.link()
+                    .map(str::trim)
+                    .filter(|link| !link.is_empty())
+                    .map(|link| Link { href: link.to_owned(), ..Default::default() });
+                link.into_iter().chain(self.enclosures()).collect()
+            }
+            Self::Atom(entry, _) => entry.links().to_vec(),
+        }
+    }
+
+    /// Returns the media attached to this entry: the enclosure of an RSS item
+    /// or the links of an Atom entry with an enclosure rel.
+    pub fn enclosures(self) -> Vec<Link> {
+        match self {
+            Self::Rss(item) => {
+                item.enclosure().into_iter()
+                    .filter(|enclosure| !enclosure.url().trim().is_empty())
+                    .map(|enclosure| Link {
+                        href: enclosure.url().trim().to_owned(),
+                        rel: "enclosure".to_owned(),
+                        mime_type: Some(enclosure.mime_type().trim())
+                            .filter(|mime_type| !mime_type.is_empty())
+                            .map(str::to_owned),
+                        length: Some(enclosure.length().trim())
+                            .filter(|length| !length.is_empty())
+                            .map(str::to_owned),
+                        ..Default::default()
+                    })
+                    .collect()
+            }
+            Self::Atom(entry, _) => {
+                entry.links().iter()
+                    .filter(|link| link.is_enclosure())
+                    .cloned()
+                    .collect()
+            }
+        }
+    }
+
     pub fn guid(self) -> Option<&'a str> {
         match self {
             Self::Rss(item) => item.guid().map(|id| id.value()),
-            Self::Atom(entry) => Some(entry.id()),
+            Self::Atom(entry, _) => Some(entry.id()),
+        }
+    }
+
+    pub fn identifier(self) -> Option<ItemIdentifier<'a>> {
+        ItemIdentifier::new(self.link(), self.guid())
+    }
+
+    /// Returns whether the guid of an RSS item is also its url.
+    /// Atom ids are never treated as permalinks.
+    pub fn guid_is_permalink(self) -> bool {
+        match self {
+            Self::Rss(item) => item.guid().is_some_and(|id| id.is_permalink()),
+            Self::Atom(..) => false,
+        }
+    }
+
+    /// Returns the categories of this entry, skipping blank and repeated terms.
+    pub fn categories(self) -> Vec<Category> {
+        let categories: Vec<(&str, Option<&str>)> = match self {
+            Self::Rss(item) if item.categories().is_empty() => {
+                item.dublin_core_ext()
+                    .map_or(&[][..], |ext| ext.subjects())
+                    .iter()
+                    .map(|subject| (subject.as_str(), None))
+                    .collect()
+            }
+            Self::Rss(item) => {
+                item.categories().iter()
+                    .map(|category| (category.name(), category.domain()))
+                    .collect()
+            }
+            Self::Atom(entry, _) => {
+                // Some feeds converted from RSS only give a label
+                entry.categories().iter()
+                    .map(|category| match category.term() {
+                        "" => (category.label().unwrap_or(""), category.scheme()),
+                        term => (term, category.scheme()),
+                    })
+                    .collect()
+            }
+        };
+
+        let mut result: Vec<Category> = Vec::new();
+        for (term, scheme) in categories {
+            let term = term.trim();
+            if term.is_empty() || result.iter().any(|c| c.term == term) {
+                continue;
+            }
+            result.push(Category {
+                term: term.to_owned(),
+                scheme: scheme.map(str::to_owned),
+            });
         }
+        result
     }
 }
@@ -1,11 +1,459 @@
+use std::borrow::Cow;
 use std::error::Error;
 use std::fmt;
+use std::io;
+use std::panic;
+use std::mem;
 use std::slice;
+use std::time::Duration;
+use std::vec;
 
 use atom_syndication as atom;
+use atom_syndication::Link;
+use chrono::{DateTime, FixedOffset};
+use md5::{Digest, Md5};
+use quick_xml::Error as XmlError;
+use quick_xml::{NsReader, Reader};
+use quick_xml::events::Event;
+use quick_xml::name::{Namespace, ResolveResult};
 use rss;
+use rss::extension::syndication::UpdatePeriod;
+use url::{ParseError, Url};
 
-use super::entry::{Entry, EntryRef};
+use super::entry::{Entry, EntryRef, effective_authors, parse_date};
+use super::link::{LinkExt, dedup_links, find_alternate};
+use super::validate::{Warning, check_absolute_iri, check_iri, check_not_empty};
+use super::visit::{Visitor, visit_feed};
+
+/// Converts a syndication module period and frequency into an interval,
+/// per http://web.resource.org/rss/1.0/modules/syndication/.
+fn syndication_interval(period: &UpdatePeriod, frequency: u32) -> Duration {
+    const HOUR: u64 = 60 * 60;
+    let period_secs = match period {
+        UpdatePeriod::Hourly => HOUR,
+        UpdatePeriod::Daily => 24 * HOUR,
+        UpdatePeriod::Weekly => 7 * 24 * HOUR,
+        UpdatePeriod::Monthly => 30 * 24 * HOUR,
+        UpdatePeriod::Yearly => 365 * 24 * HOUR,
+    };
+    Duration::from_secs(period_secs / u64::from(frequency.max(1)))
+}
+
+static ATOM_NAMESPACE: &str = "http://www.w3.org/2005/Atom";
+static ATOM_03_NAMESPACE: &str = "http://purl.org/atom/ns#";
+
+/// Atom 0.3 elements that were renamed in Atom 1.0.
+static ATOM_03_RENAMES: &[(&str, &str)] = &[
+    ("modified", "updated"),
+    ("issued", "published"),
+    ("tagline", "subtitle"),
+    ("copyright", "rights"),
+];
+
+fn is_atom_03_namespace(namespace: &ResolveResult) -> bool {
+    matches!(namespace, ResolveResult::Bound(Namespace(namespace))
+        if *namespace == ATOM_03_NAMESPACE.as_bytes())
+}
+
+/// Finds which Atom 0.3 entries have an issued date, in order,
+/// or None if the source can't be read as XML.
+fn atom_03_entries_issued(source: &[u8]) -> Option<Vec<bool>> {
+    let mut reader = NsReader::from_reader(source);
+    // Whether each open element is an Atom 0.3 entry
+    let mut open_entries: Vec<bool> = Vec::new();
+    let mut issued = Vec::new();
+    loop {
+        let (namespace, tag, is_empty) = match reader.read_resolved_event() {
+            Ok((namespace, Event::Start(tag))) => (namespace, tag, false),
+            Ok((namespace, Event::Empty(tag))) => (namespace, tag, true),
+            Ok((_, Event::End(_))) => {
+                open_entries.pop();
+                continue;
+            }
+            Ok((_, Event::Eof)) => return Some(issued),
+            Err(_) => return None,
+            Ok(_) => continue,
+        };
+
+        let is_atom = is_atom_03_namespace(&namespace);
+        let is_entry = is_atom && tag.local_name().as_ref() == b"entry";
+        if is_entry {
+            issued.push(false);
+        } else if is_atom && open_entries.last() == Some(&true) && tag.local_name().as_ref() == b"issued" {
+            if let Some(issued) = issued.last_mut() {
+                *issued = true;
+            }
+        }
+        if !is_empty {
+            open_entries.push(is_entry);
+        }
+    }
+}
+
+/// Renames the elements of a legacy Atom 0.3 feed to their Atom 1.0
+/// equivalents, so it can be read like any other Atom feed. Only children
+/// of the feed and its entries are renamed, keeping their prefix, so text,
+/// CDATA, and xhtml content are untouched. An entry's created date becomes
+/// its published date if it wasn't issued, and is dropped otherwise.
+/// Anything else is returned as-is.
+fn upgrade_atom_03(source: &[u8]) -> Cow<'_, [u8]> {
+    let namespace = ATOM_03_NAMESPACE.as_bytes();
+    if !source.windows(namespace.len()).any(|window| window == namespace) {
+        return Cow::Borrowed(source);
+    }
+    let entries_issued = match atom_03_entries_issued(source) {
+        Some(entries_issued) => entries_issued,
+        None => return Cow::Borrowed(source),
+    };
+
+    #[derive(Clone, Copy, PartialEq)]
+    enum Parent { Feed, Entry, Other }
+
+    let mut reader = NsReader::from_reader(source);
+    // What kind of parent each open element is, and the name it was renamed to
+    let mut open_tags: Vec<(Parent, Option<&str>)> = Vec::new();
+    let mut entries_issued = entries_issued.into_iter();
+    let mut entry_issued = false;
+    let mut replacements = Vec::new();
+    loop {
+        let start = reader.buffer_position();
+        let (namespace, tag, is_empty) = match reader.read_resolved_event() {
+            Ok((namespace, Event::Start(tag))) => (namespace, tag, false),
+            Ok((namespace, Event::Empty(tag))) => (namespace, tag, true),
+            Ok((_, Event::End(tag))) => {
+                if let Some((_, Some(new))) = open_tags.pop() {
+                    let name = renamed(tag.name().as_ref(), new);
+                    replacements.push((start + 2..start + 2 + tag.name().as_ref().len(), name));
+                }
+                continue;
+            }
+            Ok((_, Event::Eof)) => break,
+            Err(_) => return Cow::Borrowed(source),
+            Ok(_) => continue,
+        };
+
+        let is_atom = is_atom_03_namespace(&namespace);
+        let local_name = tag.local_name();
+        let kind = match local_name.as_ref() {
+            b"feed" if is_atom => Parent::Feed,
+            b"entry" if is_atom => {
+                entry_issued = entries_issued.next().unwrap_or(false);
+                Parent::Entry
+            }
+            _ => Parent::Other,
+        };
+        let parent = open_tags.last().map_or(Parent::Other, |&(parent, _)| parent);
+        let new = if !is_atom || parent == Parent::Other {
+            None
+        } else if parent == Parent::Entry && local_name.as_ref() == b"created" {
+            if entry_issued {
+                // Otherwise the entry would have two published dates
+                if !is_empty && reader.read_to_end(tag.name()).is_err() {
+                    return Cow::Borrowed(source);
+                }
+                replacements.push((start..reader.buffer_position(), Vec::new()));
+                continue;
+            }
+            Some("published")
+        } else {
+            ATOM_03_RENAMES.iter()
+                .find(|(old, _)| old.as_bytes() == local_name.as_ref())
+                .map(|&(_, new)| new)
+        };
+
+        if let Some(new) = new {
+            let name = renamed(tag.name().as_ref(), new);
+            replacements.push((start + 1..start + 1 + tag.name().as_ref().len(), name));
+        }
+        if !is_empty {
+            open_tags.push((kind, new));
+        }
+    }
+
+    if replacements.is_empty() {
+        return Cow::Borrowed(source);
+    }
+    let mut upgraded = Vec::with_capacity(source.len());
+    let mut kept_start = 0;
+    for (range, replacement) in replacements {
+        upgraded.extend_from_slice(&source[kept_start..range.start]);
+        upgraded.extend_from_slice(&replacement);
+        kept_start = range.end;
+    }
+    upgraded.extend_from_slice(&source[kept_start..]);
+    Cow::Owned(upgraded)
+}
+
+/// Replaces the local part of a possibly prefixed element name.
+fn renamed(name: &[u8], new: &str) -> Vec<u8> {
+    let prefix_len = name.iter().position(|&b| b == b':').map_or(0, |colon| colon + 1);
+    let mut renamed = name[..prefix_len].to_vec();
+    renamed.extend_from_slice(new.as_bytes());
+    renamed
+}
+
+/// Finds the first feed element declaring the Atom namespace that's embedded
+/// in some other document, like an HTML page or a SOAP envelope from
+/// a broken server.
+fn find_embedded_feed(source: &[u8]) -> Option<&[u8]> {
+    let xml = std::str::from_utf8(source).ok()?;
+    let start = xml.match_indices("<feed")
+        .map(|(start, _)| start)
+        .find(|&start| {
+            let tag = &xml[start..];
+            let tag = &tag[..tag.find('>').unwrap_or(tag.len())];
+            tag[5..].starts_with(|c: char| c.is_whitespace() || c == '>' || c == '/')
+                && (tag.contains(ATOM_NAMESPACE) || tag.contains(ATOM_03_NAMESPACE))
+        })?;
+    let end = xml[start..].find("</feed>")? + start + "</feed>".len();
+    Some(&source[start..end])
+}
+
+/// Cuts the source off before the entry after the first max_entries, closing
+/// the elements that were still open, so the rest of a large feed isn't parsed.
+/// Sources that can't be read as XML are returned as-is for parsing to report.
+fn truncate_entries(source: &[u8], max_entries: usize) -> Cow<'_, [u8]> {
+    let mut reader = Reader::from_reader(source);
+    let mut open_tags: Vec<Vec<u8>> = Vec::new();
+    let mut entry_count = 0;
+    loop {
+        let start = reader.buffer_position();
+        let (tag, is_empty) = match reader.read_event() {
+            Ok(Event::Start(tag)) => (tag, false),
+            Ok(Event::Empty(tag)) => (tag, true),
+            Ok(Event::End(_)) => {
+                open_tags.pop();
+                continue;
+            }
+            Ok(Event::Eof) | Err(_) => return Cow::Borrowed(source),
+            Ok(_) => continue,
+        };
+
+        // Entries are children of an Atom feed, RSS channel, or RSS 1.0 root
+        let is_entry = matches!(tag.local_name().as_ref(), b"entry" | b"item");
+        if is_entry && open_tags.len() <= 2 {
+            if entry_count == max_entries {
+                let mut truncated = source[..start].to_vec();
+                for name in open_tags.iter().rev() {
+                    truncated.extend_from_slice(b"</");
+                    truncated.extend_from_slice(name);
+                    truncated.push(b'>');
+                }
+                return Cow::Owned(truncated);
+            }
+            entry_count += 1;
+        }
+        if !is_empty {
+            open_tags.push(tag.name().as_ref().to_vec());
+        }
+    }
+}
+
+/// Gives Atom categories that only have text, like ones converted from RSS,
+/// that text as their term, since atom_syndication ignores it. Categories
+/// with a term attribute are left alone, even if they also have text.
+/// Sources that can't be read as XML are returned as-is for parsing to report.
+fn category_text_terms(source: &[u8]) -> Cow<'_, [u8]> {
+    if !source.windows(b"category>".len()).any(|window| window == b"category>") {
+        return Cow::Borrowed(source);
+    }
+
+    let mut reader = NsReader::from_reader(source);
+    let mut replacements = Vec::new();
+    loop {
+        let start = reader.buffer_position();
+        let tag = match reader.read_resolved_event() {
+            Ok((namespace, Event::Start(tag))) if is_atom_namespace(&namespace) => tag,
+            Ok((_, Event::Eof)) => break,
+            Err(_) => return Cow::Borrowed(source),
+            Ok(_) => continue,
+        };
+        if tag.local_name().as_ref() != b"category" {
+            continue;
+        }
+        let has_term = match tag.try_get_attribute("term") {
+            Ok(term) => term.is_some(),
+            Err(_) => return Cow::Borrowed(source),
+        };
+        let text = match reader.read_text(tag.name()) {
+            Ok(text) => text,
+            Err(_) => return Cow::Borrowed(source),
+        };
+        // Text with markup in it, like CDATA, isn't moved into an attribute
+        let text = text.trim();
+        if has_term || text.is_empty() || text.contains('<') {
+            continue;
+        }
+
+        let mut category = b"<".to_vec();
+        category.extend_from_slice(tag.trim_ascii_end());
+        category.extend_from_slice(b" term=\"");
+        category.extend_from_slice(text.replace('"', "&quot;").as_bytes());
+        category.extend_from_slice(b"\"/>");
+        replacements.push((start..reader.buffer_position(), category));
+    }
+
+    if replacements.is_empty() {
+        return Cow::Borrowed(source);
+    }
+    let mut replaced = Vec::with_capacity(source.len());
+    let mut kept_start = 0;
+    for (range, replacement) in replacements {
+        replaced.extend_from_slice(&source[kept_start..range.start]);
+        replaced.extend_from_slice(&replacement);
+        kept_start = range.end;
+    }
+    replaced.extend_from_slice(&source[kept_start..]);
+    Cow::Owned(replaced)
+}
+
+/// Cheaply checks whether any entry might have more than one content
+/// element, so most feeds don't need to be read twice. Prefixed names and
+/// tag-like text can give false positives, which drop_duplicate_content
+/// rules out.
+fn may_have_duplicate_content(source: &[u8]) -> bool {
+    let mut content_count = 0;
+    for tag in source.split(|&b| b == b'<').skip(1) {
+        let name_len = tag.iter()
+            .position(|&b| b.is_ascii_whitespace() || b == b'>' || b == b'/')
+            .unwrap_or(tag.len());
+        let name = &tag[..name_len];
+        match name.rsplit(|&b| b == b':').next().unwrap_or(name) {
+            b"entry" => content_count = 0,
+            b"content" => {
+                content_count += 1;
+                if content_count > 1 {
+                    return true;
+                }
+            }
+            _ => (),
+        }
+    }
+    false
+}
+
+fn is_atom_namespace(namespace: &ResolveResult) -> bool {
+    match namespace {
+        ResolveResult::Bound(Namespace(namespace)) => {
+            *namespace == ATOM_NAMESPACE.as_bytes() || *namespace == ATOM_03_NAMESPACE.as_bytes()
+        }
+        _ => false,
+    }
+}
+
+/// Finds Atom entries with more than one content element, which Atom
+/// doesn't allow. Strict parsing rejects them, otherwise only the first
+/// content of each entry is kept, since atom_syndication would keep the last,
+/// and a warning is returned for each entry that had more.
+/// Sources that can't be read as XML are returned as-is for parsing to report.
+fn drop_duplicate_content(source: &[u8], strict: bool)
+-> Result<(Cow<'_, [u8]>, Vec<Warning>), FeedParseError> {
+    let unchanged = Ok((Cow::Borrowed(source), Vec::new()));
+    if !may_have_duplicate_content(source) {
+        return unchanged;
+    }
+
+    let mut reader = NsReader::from_reader(source);
+    // Whether each open element is an Atom entry
+    let mut open_entries: Vec<bool> = Vec::new();
+    let mut content_count = 0;
+    let mut duplicates = Vec::new();
+    let mut warnings = Vec::new();
+    loop {
+        let start = reader.buffer_position();
+        let (is_atom, tag, is_empty) = match reader.read_resolved_event() {
+            Ok((namespace, Event::Start(tag))) => (is_atom_namespace(&namespace), tag, false),
+            Ok((namespace, Event::Empty(tag))) => (is_atom_namespace(&namespace), tag, true),
+            Ok((_, Event::End(_))) => {
+                open_entries.pop();
+                continue;
+            }
+            Ok((_, Event::Eof)) => break,
+            Err(_) => return unchanged,
+            Ok(_) => continue,
+        };
+
+        let is_entry = is_atom && tag.local_name().as_ref() == b"entry";
+        let in_entry = open_entries.last() == Some(&true);
+        if is_entry {
+            content_count = 0;
+        } else if is_atom && in_entry && tag.local_name().as_ref() == b"content" {
+            content_count += 1;
+            if content_count > 1 {
+                if strict {
+                    return Err(FeedParseError::DuplicateElement("content"));
+                }
+                if content_count == 2 {
+                    warnings.push(Warning {
+                        element: "content",
+                        message: "appears more than once in an entry, only the first is kept"
+                            .to_owned(),
+                    });
+                }
+                if !is_empty && reader.read_to_end(tag.name()).is_err() {
+                    return unchanged;
+                }
+                duplicates.push(start..reader.buffer_position());
+                continue;
+            }
+        }
+        if !is_empty {
+            open_entries.push(is_entry);
+        }
+    }
+
+    if duplicates.is_empty() {
+        return unchanged;
+    }
+    let mut deduped = Vec::with_capacity(source.len());
+    let mut kept_start = 0;
+    for duplicate in duplicates {
+        deduped.extend_from_slice(&source[kept_start..duplicate.start]);
+        kept_start = duplicate.end;
+    }
+    deduped.extend_from_slice(&source[kept_start..]);
+    Ok((Cow::Owned(deduped), warnings))
+}
+
+/// Reads an Atom feed, falling back to a feed embedded in another document.
+fn read_atom(source: &[u8]) -> Result<atom::Feed, atom::Error> {
+    match atom::Feed::read_from(source) {
+        Err(atom::Error::InvalidStartTag) => {
+            match find_embedded_feed(source) {
+                Some(feed) => atom::Feed::read_from(feed),
+                None => Err(atom::Error::InvalidStartTag),
+            }
+        }
+        Err(atom::Error::Xml(_)) if ends_early(source) => Err(atom::Error::Eof),
+        result => result,
+    }
+}
+
+/// Returns whether the XML in source stops before closing all of its
+/// elements, like from an interrupted download.
+fn ends_early(source: &[u8]) -> bool {
+    let mut reader = Reader::from_reader(source);
+    let mut depth = 0usize;
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(_)) => depth += 1,
+            Ok(Event::End(_)) => depth = depth.saturating_sub(1),
+            Ok(Event::Eof) => return depth > 0,
+            Err(XmlError::UnexpectedEof(_)) => return true,
+            Err(_) => return false,
+            Ok(_) => (),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ParseOptions {
+    /// Stops reading a feed after this many entries, like for a preview.
+    pub max_entries: Option<usize>,
+    /// Rejects malformed feeds that are otherwise read leniently,
+    /// like entries with more than one content element.
+    pub strict: bool,
+}
 
 pub enum Feed {
     Rss(rss::Channel),
@@ -14,17 +462,45 @@ pub enum Feed {
 
 impl Feed {
     pub fn parse(source: &[u8]) -> Result<Feed, FeedParseError> {
-        match rss::Channel::read_from(source) {
-            Ok(channel) => Ok(Feed::Rss(channel)),
+        Feed::parse_with(source, &ParseOptions::default())
+    }
+
+    pub fn parse_with(source: &[u8], options: &ParseOptions) -> Result<Feed, FeedParseError> {
+        Feed::parse_with_warnings(source, options).map(|(feed, _)| feed)
+    }
+
+    /// Parses a feed like parse_with, also returning the problems that were
+    /// worked around to read it, like entries with more than one content.
+    pub fn parse_with_warnings(source: &[u8], options: &ParseOptions)
+    -> Result<(Feed, Vec<Warning>), FeedParseError> {
+        let source = match options.max_entries {
+            Some(max_entries) => truncate_entries(source, max_entries),
+            None => Cow::Borrowed(source),
+        };
+        match rss::Channel::read_from(&*source) {
+            Ok(channel) => Ok((Feed::Rss(channel), Vec::new())),
             Err(rss::Error::InvalidStartTag) => {
-                atom::Feed::read_from(source)
-                    .map(Feed::Atom)
+                let source = upgrade_atom_03(&source);
+                let source = category_text_terms(&source);
+                let (source, warnings) = drop_duplicate_content(&source, options.strict)?;
+                read_atom(&source)
+                    .map(|feed| (Feed::Atom(feed), warnings))
                     .map_err(FeedParseError::Atom)
             }
             Err(err) => Err(FeedParseError::Rss(err)),
         }
     }
 
+    /// Parses a feed on a blocking thread so that parsing a large feed
+    /// doesn't block other tasks running on the async runtime.
+    pub async fn parse_async<B>(source: B) -> Result<Feed, FeedParseError>
+    where B: AsRef<[u8]> + Send + 'static {
+        let result = tokio::task::spawn_blocking(move || {
+            Feed::parse(source.as_ref())
+        }).await;
+        result.unwrap_or_else(|err| panic::resume_unwind(err.into_panic()))
+    }
+
     pub fn title(&self) -> &str {
         match self {
             Feed::Rss(channel) => channel.title(),
@@ -32,18 +508,85 @@ impl Feed {
         }.trim()
     }
 
+    pub fn subtitle(&self) -> Option<&str> {
+        match self {
+            Feed::Rss(channel) => Some(channel.description()),
+            Feed::Atom(feed) => feed.subtitle().map(|subtitle| subtitle.as_str()),
+        }.map(str::trim).filter(|subtitle| !subtitle.is_empty())
+    }
+
+    /// Returns the name of the software that generated this feed.
+    /// Generators are only informational, so an empty one, even with
+    /// a uri or version, is treated as missing.
+    pub fn generator(&self) -> Option<&str> {
+        match self {
+            Feed::Rss(channel) => channel.generator(),
+            Feed::Atom(feed) => feed.generator().map(|generator| generator.value()),
+        }.map(str::trim).filter(|generator| !generator.is_empty())
+    }
+
+    /// Returns every link of this feed. RSS channels only have a link,
+    /// which is treated as the alternate.
+    pub fn links(&self) -> Cow<'_, [Link]> {
+        match self {
+            Feed::Rss(channel) => {
+                let link = Some(channel.link().trim())
+                    .filter(|link| !link.is_empty())
+                    .map(|link| Link { href: link.to_owned(), ..Default::default() });
+                Cow::Owned(link.into_iter().collect())
+            }
+            Feed::Atom(feed) => Cow::Borrowed(feed.links()),
+        }
+    }
+
     pub fn site_url(&self) -> Option<&str> {
         match self {
             Feed::Rss(channel) => Some(channel.link()),
             Feed::Atom(feed) => {
-                feed.links()
-                    .iter().filter(|link| link.rel() == "alternate").next()
-                    .or(feed.links().first())
+                find_alternate(feed.links())
                     .map(|link| link.href())
             }
         }.map(str::trim)
     }
 
+    /// Returns the url that relative links in this feed should be resolved against,
+    /// given the url the feed was fetched from.
+    pub fn base_url(&self, feed_url: &str) -> Result<Url, ParseError> {
+        let mut base_url = Url::parse(feed_url)?;
+        if let Feed::Atom(feed) = self {
+            if let Some(xml_base) = feed.base() {
+                base_url = base_url.join(xml_base.trim())?;
+            }
+        }
+        // Some bad feeds use links relative to their site rather than the feed
+        if let Some(site_url) = self.site_url() {
+            base_url = base_url.join(site_url)?;
+        }
+        Ok(base_url)
+    }
+
+    /// Returns the link to the page of older entries from an archived or paged
+    /// feed, per RFC 5005. Only Atom links are supported.
+    pub fn archive_url(&self) -> Option<&str> {
+        match self {
+            Feed::Rss(_) => None,
+            Feed::Atom(feed) => {
+                let links = feed.links();
+                links.iter()
+                    .find(|link| link.rel_is("prev-archive"))
+                    .or_else(|| links.iter().find(|link| link.rel_is("next")))
+                    .map(|link| link.href().trim())
+            }
+        }
+    }
+
+    /// Returns the archive url of this page resolved against the url
+    /// the page was fetched from.
+    pub fn resolved_archive_url(&self, page_url: &str) -> Option<Url> {
+        let archive_url = self.archive_url()?;
+        Url::parse(page_url).ok()?.join(archive_url).ok()
+    }
+
     pub fn len(&self) -> usize {
         match self {
             Feed::Rss(channel) => channel.items().len(),
@@ -51,21 +594,256 @@ impl Feed {
         }
     }
 
-    pub fn entries<'a>(&'a self) -> impl Iterator<Item=Entry> + 'a {
+    /// Checks that the urls in this feed are valid IRIs.
+    /// Invalid values are kept as-is when parsing, so this only reports them.
+    pub fn validate(&self) -> Vec<Warning> {
+        let mut warnings = Vec::new();
+        match self {
+            Feed::Rss(channel) => {
+                check_iri("link", channel.link(), &mut warnings);
+                if let Some(image) = channel.image() {
+                    check_iri("image", image.url(), &mut warnings);
+                }
+                for item in channel.items() {
+                    if let Some(link) = item.link() {
+                        check_iri("link", link, &mut warnings);
+                    }
+                }
+            }
+            Feed::Atom(feed) => {
+                if let Some(icon) = feed.icon() {
+                    check_iri("icon", icon, &mut warnings);
+                }
+                if let Some(logo) = feed.logo() {
+                    check_iri("logo", logo, &mut warnings);
+                }
+                let entry_links = feed.entries().iter()
+                    .flat_map(|entry| entry.links());
+                for link in feed.links().iter().chain(entry_links) {
+                    check_iri("link", link.href(), &mut warnings);
+                }
+                let sources = feed.entries().iter()
+                    .filter_map(|entry| entry.source());
+                for source in sources {
+                    if let Some(icon) = source.icon() {
+                        check_iri("icon", icon, &mut warnings);
+                    }
+                    if let Some(logo) = source.logo() {
+                        check_iri("logo", logo, &mut warnings);
+                    }
+                }
+            }
+        }
+        warnings
+    }
+
+    /// Checks for entries missing information that has to be made up when
+    /// they're ingested: a date to sort them by, or anything to identify them.
+    pub fn validate_entries(&self) -> Vec<Warning> {
+        let (date_element, id_element) = match self {
+            Feed::Rss(_) => ("pubDate", "guid"),
+            Feed::Atom(_) => ("updated", "id"),
+        };
+
+        let mut warnings = Vec::new();
+        for entry in self.entry_refs() {
+            if entry.published().is_none() {
+                warnings.push(Warning {
+                    element: date_element,
+                    message: format!("entry {:?} has no valid date", entry.title()),
+                });
+            }
+            if entry.identifier().is_none() {
+                warnings.push(Warning {
+                    element: id_element,
+                    message: format!("entry {:?} has no link or id to identify it by", entry.title()),
+                });
+            }
+        }
+        warnings
+    }
+
+    /// Checks for common mistakes in an Atom feed: ids that aren't absolute,
+    /// empty required elements, and entries without any author to inherit.
+    /// Dates are normalized while parsing, so their original format can't be
+    /// checked here.
+    pub fn validate_atom(&self) -> Vec<Warning> {
+        let feed = match self {
+            Feed::Rss(_) => {
+                return vec![Warning {
+                    element: "rss",
+                    message: "is an RSS feed, not Atom".to_owned(),
+                }];
+            }
+            Feed::Atom(feed) => feed,
+        };
+
+        let mut warnings = Vec::new();
+        check_not_empty("id", feed.id(), &mut warnings);
+        check_absolute_iri("id", feed.id(), &mut warnings);
+        check_not_empty("title", feed.title(), &mut warnings);
+        for entry in feed.entries() {
+            check_not_empty("id", entry.id(), &mut warnings);
+            check_absolute_iri("id", entry.id(), &mut warnings);
+            check_not_empty("title", entry.title(), &mut warnings);
+            if effective_authors(entry, Some(feed)).is_empty() {
+                warnings.push(Warning {
+                    element: "author",
+                    message: format!("entry {:?} has no author of its own or to inherit", entry.id()),
+                });
+            }
+        }
+        warnings
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the entries of this feed borrowed in place, for reading
+    /// a few fields without copying every entry into an owned Entry.
+    pub fn entry_refs(&self) -> Entries<'_> {
         match *self {
             Feed::Rss(ref channel) => {
                 Entries::Rss(channel.items().iter())
             }
             Feed::Atom(ref feed) => {
-                Entries::Atom(feed.entries().iter())
+                Entries::Atom(feed.entries().iter(), feed)
+            }
+        }
+    }
+
+    pub fn entries<'a>(&'a self) -> impl Iterator<Item=Entry> + 'a {
+        self.entry_refs().map(Entry::from_ref)
+    }
+
+    /// Moves the entries out of this feed, leaving it empty.
+    pub fn take_entries(&mut self) -> Vec<Entry> {
+        self.drain_entries().collect()
+    }
+
+    /// Moves the entries out of this feed as they're iterated,
+    /// leaving it empty.
+    pub fn drain_entries(&mut self) -> DrainEntries<'_> {
+        match self {
+            Feed::Rss(channel) => {
+                DrainEntries::Rss(mem::take(&mut channel.items).into_iter())
+            }
+            Feed::Atom(feed) => {
+                let entries = mem::take(&mut feed.entries);
+                DrainEntries::Atom(entries.into_iter(), feed)
+            }
+        }
+    }
+
+    /// Removes links repeating an earlier one's href, rel, and type from
+    /// this feed, its entries, and their sources. RSS feeds are left as-is,
+    /// since they only ever have one link in each place.
+    pub fn dedup_links(&mut self) {
+        if let Feed::Atom(feed) = self {
+            dedup_links(&mut feed.links);
+            for entry in &mut feed.entries {
+                dedup_links(&mut entry.links);
+                if let Some(source) = &mut entry.source {
+                    dedup_links(&mut source.links);
+                }
+            }
+        }
+    }
+
+    /// Walks every person, link, and category of this feed, its entries,
+    /// and their sources. RSS has none of these Atom constructs,
+    /// so nothing is visited for RSS feeds.
+    pub fn accept<V: Visitor + ?Sized>(&self, visitor: &mut V) {
+        if let Feed::Atom(feed) = self {
+            visit_feed(feed, visitor);
+        }
+    }
+
+    /// Returns a hash of the ids and updated dates of this feed's entries,
+    /// which is stable across runs so it can be stored to detect changes.
+    pub fn fingerprint(&self) -> u64 {
+        let mut hash = Md5::new();
+        for entry in self.entry_refs() {
+            let id = entry.guid().or(entry.link()).unwrap_or("");
+            hash.update(id.as_bytes());
+            hash.update([0]);
+            if let Some(updated) = entry.updated() {
+                hash.update(updated.timestamp().to_be_bytes());
+            }
+            hash.update([0]);
+        }
+
+        let digest = hash.finalize();
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(&digest[..8]);
+        u64::from_be_bytes(bytes)
+    }
+
+    /// Returns how often this feed says it should be polled,
+    /// from the RSS ttl element or the syndication module's updatePeriod.
+    pub fn update_interval(&self) -> Option<Duration> {
+        match self {
+            Feed::Rss(channel) => {
+                let ttl = channel.ttl()
+                    .and_then(|ttl| ttl.trim().parse::<u64>().ok())
+                    .filter(|&minutes| minutes > 0)
+                    .map(|minutes| Duration::from_secs(minutes * 60));
+                ttl.or_else(|| {
+                    let ext = channel.syndication_ext()?;
+                    Some(syndication_interval(ext.period(), ext.frequency()))
+                })
+            }
+            Feed::Atom(feed) => {
+                let ext = feed.extensions().get("sy")?;
+                let value = |name| {
+                    ext.get(name)
+                        .and_then(|exts| exts.first())
+                        .and_then(|ext| ext.value())
+                };
+                let frequency = value("updateFrequency")
+                    .and_then(|frequency| frequency.trim().parse().ok())
+                    .unwrap_or(1);
+                let period = value("updatePeriod")?.trim().parse().ok()?;
+                Some(syndication_interval(&period, frequency))
+            }
+        }
+    }
+
+    /// Returns the most recent date among this feed's own updated date
+    /// and the updated and published dates of its entries,
+    /// ignoring any dates that can't be parsed.
+    pub fn latest_updated(&self) -> Option<DateTime<FixedOffset>> {
+        let feed_updated = match self {
+            Feed::Rss(channel) => {
+                channel.last_build_date().into_iter()
+                    .chain(channel.pub_date())
+                    .filter_map(parse_date)
+                    .max()
             }
-        }.map(Entry::from_ref)
+            Feed::Atom(feed) => Some(*feed.updated()),
+        };
+
+        self.entry_refs()
+            .flat_map(|entry| [entry.updated(), entry.published()])
+            .flatten()
+            .chain(feed_updated)
+            .max()
+    }
+
+    /// Returns the entries updated after the given date, or published after
+    /// it if they have no updated date, skipping any entries without a valid date.
+    /// The entries are borrowed in place, like from entry_refs.
+    pub fn entries_since(&self, since: DateTime<FixedOffset>) -> Vec<EntryRef<'_>> {
+        self.entry_refs()
+            .filter(|entry| entry.updated().or(entry.published()).is_some_and(|date| date > since))
+            .collect()
     }
 }
 
-enum Entries<'a> {
+pub enum Entries<'a> {
     Rss(slice::Iter<'a, rss::Item>),
-    Atom(slice::Iter<'a, atom::Entry>),
+    Atom(slice::Iter<'a, atom::Entry>, &'a atom::Feed),
 }
 
 impl<'a> Iterator for Entries<'a> {
@@ -76,8 +854,73 @@ impl<'a> Iterator for Entries<'a> {
             Entries::Rss(items) => {
                 items.next().map(EntryRef::Rss)
             }
-            Entries::Atom(entries) => {
-                entries.next().map(EntryRef::Atom)
+            Entries::Atom(entries, feed) => {
+                entries.next().map(|entry| EntryRef::Atom(entry, feed))
+            }
+        }
+    }
+}
+
+pub enum DrainEntries<'a> {
+    Rss(vec::IntoIter<rss::Item>),
+    Atom(vec::IntoIter<atom::Entry>, &'a atom::Feed),
+}
+
+impl<'a> Iterator for DrainEntries<'a> {
+    type Item = Entry;
+
+    fn next(&mut self) -> Option<Entry> {
+        match self {
+            DrainEntries::Rss(items) => {
+                items.next().map(Entry::from_rss)
+            }
+            DrainEntries::Atom(entries, feed) => {
+                entries.next().map(|entry| Entry::from_atom(entry, feed))
+            }
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a Feed {
+    type Item = EntryRef<'a>;
+    type IntoIter = Entries<'a>;
+
+    fn into_iter(self) -> Entries<'a> {
+        self.entry_refs()
+    }
+}
+
+pub enum IntoEntries {
+    Rss(vec::IntoIter<rss::Item>),
+    // The feed is kept for entries that inherit its authors
+    Atom(vec::IntoIter<atom::Entry>, Box<atom::Feed>),
+}
+
+impl Iterator for IntoEntries {
+    type Item = Entry;
+
+    fn next(&mut self) -> Option<Entry> {
+        match self {
+            IntoEntries::Rss(items) => {
+                items.next().map(Entry::from_rss)
+            }
+            IntoEntries::Atom(entries, feed) => {
+                entries.next().map(|entry| Entry::from_atom(entry, feed))
+            }
+        }
+    }
+}
+
+impl IntoIterator for Feed {
+    type Item = Entry;
+    type IntoIter = IntoEntries;
+
+    fn into_iter(self) -> IntoEntries {
+        match self {
+            Feed::Rss(channel) => IntoEntries::Rss(channel.items.into_iter()),
+            Feed::Atom(mut feed) => {
+                let entries = mem::take(&mut feed.entries);
+                IntoEntries::Atom(entries.into_iter(), Box::new(feed))
             }
         }
     }
@@ -87,6 +930,31 @@ impl<'a> Iterator for Entries<'a> {
 pub enum FeedParseError {
     Rss(rss::Error),
     Atom(atom::Error),
+    /// An element that's only allowed once appeared more than once.
+    DuplicateElement(&'static str),
+}
+
+impl FeedParseError {
+    /// Returns whether fetching the feed again might succeed, because the
+    /// source ended early, like from an interrupted download. Errors from
+    /// a malformed document or one that isn't a feed at all aren't recoverable.
+    pub fn is_recoverable(&self) -> bool {
+        match self {
+            FeedParseError::Rss(rss::Error::Eof) |
+            FeedParseError::Atom(atom::Error::Eof) => true,
+            FeedParseError::Rss(rss::Error::Xml(err)) => {
+                matches!(err, XmlError::Io(_) | XmlError::UnexpectedEof(_))
+            }
+            // atom_syndication hides the xml error it wraps, so only its
+            // source is available to check; a truncated source is
+            // reported as Eof by read_atom instead
+            FeedParseError::Atom(atom::Error::Xml(err)) => {
+                err.source().is_some_and(|err| err.is::<io::Error>())
+            }
+            FeedParseError::Rss(_) | FeedParseError::Atom(_) |
+            FeedParseError::DuplicateElement(_) => false,
+        }
+    }
 }
 
 impl fmt::Display for FeedParseError {
@@ -94,6 +962,9 @@ impl fmt::Display for FeedParseError {
         match self {
             FeedParseError::Rss(err) => fmt::Display::fmt(err, f),
             FeedParseError::Atom(err) => fmt::Display::fmt(err, f),
+            FeedParseError::DuplicateElement(name) => {
+                write!(f, "duplicate <{}> element", name)
+            }
         }
     }
 }
@@ -104,14 +975,22 @@ impl Error for FeedParseError {
         match self {
             FeedParseError::Rss(err) => Some(err),
             FeedParseError::Atom(err) => Some(err),
+            FeedParseError::DuplicateElement(_) => None,
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::borrow::Cow;
+    use std::sync::{Arc, Mutex};
+    use std::thread::{self, ThreadId};
+    use std::time::Duration;
+
+    use atom_syndication as atom;
     use chrono::{TimeZone, Utc};
-    use super::Feed;
+    use super::{Feed, FeedParseError, ParseOptions, upgrade_atom_03};
+    use super::super::LinkExt;
 
     static RSS_STR: &'static str = r#"
 <?xml version="1.0" encoding="UTF-8"?>
@@ -143,31 +1022,1334 @@ mod tests {
 </feed>
 "#;
 
-    #[test]
-    fn test_rss_stream() {
-        let feed = Feed::parse(RSS_STR.as_bytes()).unwrap();
-        let mut entries = feed.entries();
+    static ATOM_SUMMARY_CONTENT_STR: &'static str = r#"
+<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <id>urn:uuid:b3420f84-6bdf-4f46-a225-f1b9a14703b6</id>
+  <title>TechCrunch</title>
+  <updated>2019-04-01T07:30:00Z</updated>
+  <entry>
+    <id>urn:uuid:4ae8550b-2987-49fa-9f8c-54c180c418ac</id>
+    <title>Ford hires Elon Musk as CEO</title>
+    <updated>2019-04-01T07:30:00Z</updated>
+    <summary>In an unprecedented move, Ford hires Elon Musk.</summary>
+  </entry>
+  <entry>
+    <id>urn:uuid:9b1d4fc3-5a8e-4b0c-8c2e-2b7f1d3e6a10</id>
+    <title>Elon Musk leaves Ford</title>
+    <updated>2019-04-02T07:30:00Z</updated>
+    <content type="html">&lt;p&gt;Elon Musk has left Ford.&lt;/p&gt;</content>
+  </entry>
+</feed>
+"#;
 
-        let entry = entries.next().unwrap();
-        assert_eq!(entry.title, "Ford hires Elon Musk as CEO");
-        assert_eq!(entry.content, "In an unprecedented move, Ford hires Elon Musk.");
-        let expected_date = Utc.with_ymd_and_hms(2019, 4, 1, 7, 30, 0).unwrap();
-        assert_eq!(entry.published.unwrap(), expected_date);
+    static ATOM_COMMENTS_STR: &'static str = r#"
+<?xml version="1.0" encoding="utf-8"?>
+<?xml-stylesheet type="text/xsl" href="/feed.xsl"?>
+<!-- Generated by a static site generator -->
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <id>urn:uuid:b3420f84-6bdf-4f46-a225-f1b9a14703b6</id>
+  <!-- Feed metadata -->
+  <title>TechCrunch</title>
+  <updated>2019-04-01T07:30:00Z</updated>
+  <entry>
+    <id>urn:uuid:4ae8550b-2987-49fa-9f8c-54c180c418ac</id>
+    <title>Ford hires Elon Musk as CEO</title>
+    <updated>2019-04-01T07:30:00Z</updated>
+  </entry>
+  <!-- Older entries -->
+  <?pagination page="1"?>
+  <entry>
+    <id>urn:uuid:9b1d4fc3-5a8e-4b0c-8c2e-2b7f1d3e6a10</id>
+    <!-- Entry title -->
+    <title>Elon Musk leaves Ford</title>
+    <updated>2019-04-02T07:30:00Z</updated>
+  </entry>
+</feed>
+"#;
 
-        assert!(entries.next().is_none());
-    }
+    static ATOM_INVALID_ICON_STR: &'static str = r#"
+<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <id>urn:uuid:b3420f84-6bdf-4f46-a225-f1b9a14703b6</id>
+  <title>TechCrunch</title>
+  <updated>2019-04-01T07:30:00Z</updated>
+  <icon>http://[techcrunch.com/favicon.ico</icon>
+  <logo>/logo.png</logo>
+  <link href="http://techcrunch.com"/>
+</feed>
+"#;
+
+    static ATOM_RELATIVE_STR: &'static str = r#"
+<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom" xml:base="http://techcrunch.com/blog/">
+  <id>urn:uuid:b3420f84-6bdf-4f46-a225-f1b9a14703b6</id>
+  <title>TechCrunch</title>
+  <updated>2019-04-01T07:30:00Z</updated>
+  <entry>
+    <id>urn:uuid:4ae8550b-2987-49fa-9f8c-54c180c418ac</id>
+    <title>Ford hires Elon Musk as CEO</title>
+    <link href="/post/1"/>
+    <updated>2019-04-01T07:30:00Z</updated>
+  </entry>
+  <entry>
+    <id>urn:uuid:9b1d4fc3-5a8e-4b0c-8c2e-2b7f1d3e6a10</id>
+    <title>Elon Musk leaves Ford</title>
+    <link href="post/2"/>
+    <updated>2019-04-02T07:30:00Z</updated>
+  </entry>
+</feed>
+"#;
+
+    static ATOM_RFC822_STR: &'static str = r#"
+<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <id>urn:uuid:b3420f84-6bdf-4f46-a225-f1b9a14703b6</id>
+  <title>TechCrunch</title>
+  <updated>Sat, 17 Sep 2016 19:18:32 GMT</updated>
+  <entry>
+    <id>urn:uuid:4ae8550b-2987-49fa-9f8c-54c180c418ac</id>
+    <title>Ford hires Elon Musk as CEO</title>
+    <updated>Sat, 17 Sep 2016 19:18:32 GMT</updated>
+  </entry>
+</feed>
+"#;
+
+    static RSS_RFC3339_STR: &'static str = r#"
+<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0">
+  <channel>
+    <title>TechCrunch</title>
+    <link>http://techcrunch.com</link>
+    <description>The latest technology news and information on startups</description>
+    <item>
+      <title>Ford hires Elon Musk as CEO</title>
+      <pubDate>2016-09-17T19:18:32Z</pubDate>
+    </item>
+    <item>
+      <title>Elon Musk leaves Ford</title>
+      <pubDate>Mon, 17 Sep 2016 19:18:32 GMT</pubDate>
+    </item>
+  </channel>
+</rss>
+"#;
+
+    static RSS_DATES_STR: &'static str = r#"
+<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0">
+  <channel>
+    <title>TechCrunch</title>
+    <link>http://techcrunch.com</link>
+    <description>The latest technology news and information on startups</description>
+    <item>
+      <title>Elon Musk leaves Ford</title>
+      <pubDate>02 Apr 2019 07:30:00 GMT</pubDate>
+    </item>
+    <item>
+      <title>Ford hires Elon Musk as CEO</title>
+      <pubDate>01 Apr 2019 07:30:00 GMT</pubDate>
+    </item>
+    <item>
+      <title>Ford announces new CEO</title>
+      <pubDate>yesterday</pubDate>
+    </item>
+    <item>
+      <title>Ford stock rises</title>
+      <pubDate>31 Mar 2019 07:30:00 GMT</pubDate>
+    </item>
+  </channel>
+</rss>
+"#;
+
+    fn large_rss_feed(len: usize) -> String {
+        let items: String = (0..len).map(|i| format!(r#"
+    <item>
+      <title>Item {}</title>
+      <guid>urn:item:{}</guid>
+      <description>{}</description>
+    </item>"#, i, i, "Lorem ipsum dolor sit amet. ".repeat(50))).collect();
+        format!(r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0">
+  <channel>
+    <title>Large</title>
+    <link>http://example.com</link>
+    <description>A large feed</description>{}
+  </channel>
+</rss>"#, items)
+    }
+
+    static ATOM_AUTHORS_STR: &'static str = r#"
+<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <id>urn:uuid:b3420f84-6bdf-4f46-a225-f1b9a14703b6</id>
+  <title>TechCrunch</title>
+  <updated>2019-04-01T07:30:00Z</updated>
+  <author><name>Feed Author</name></author>
+  <entry>
+    <id>urn:uuid:4ae8550b-2987-49fa-9f8c-54c180c418ac</id>
+    <title>Ford hires Elon Musk as CEO</title>
+    <updated>2019-04-01T07:30:00Z</updated>
+    <author><name>Entry Author</name></author>
+    <source>
+      <author><name>Source Author</name></author>
+    </source>
+  </entry>
+  <entry>
+    <id>urn:uuid:9b1d4fc3-5a8e-4b0c-8c2e-2b7f1d3e6a10</id>
+    <title>Elon Musk leaves Ford</title>
+    <updated>2019-04-02T07:30:00Z</updated>
+    <source>
+      <author><name>Source Author</name></author>
+    </source>
+  </entry>
+  <entry>
+    <id>urn:uuid:0c2f6e8a-3d41-4f7b-9e5a-6b8c7d9e0f12</id>
+    <title>Ford stock rises</title>
+    <updated>2019-04-03T07:30:00Z</updated>
+  </entry>
+</feed>
+"#;
+
+    static RSS_CONTENT_ENCODED_STR: &'static str = r#"
+<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0" xmlns:content="http://purl.org/rss/1.0/modules/content/">
+  <channel>
+    <title>TechCrunch</title>
+    <link>http://techcrunch.com</link>
+    <description>The latest technology news and information on startups</description>
+    <item>
+      <title>Ford hires Elon Musk as CEO</title>
+      <description>In an unprecedented move...</description>
+      <content:encoded><![CDATA[<p>In an unprecedented move, Ford hires Elon Musk.</p>]]></content:encoded>
+    </item>
+  </channel>
+</rss>
+"#;
+
+    static RSS_CATEGORIES_STR: &'static str = r#"
+<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0">
+  <channel>
+    <title>TechCrunch</title>
+    <link>http://techcrunch.com</link>
+    <description>The latest technology news and information on startups</description>
+    <item>
+      <title>Ford hires Elon Musk as CEO</title>
+      <category>Automotive</category>
+      <category domain="http://techcrunch.com/tags">People</category>
+      <category>Automotive</category>
+    </item>
+  </channel>
+</rss>
+"#;
+
+    static ATOM_LINT_STR: &'static str = r#"
+<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <id>urn:uuid:b3420f84-6bdf-4f46-a225-f1b9a14703b6</id>
+  <title>TechCrunch</title>
+  <updated>2019-04-01T07:30:00Z</updated>
+  <entry>
+    <id>/posts/1</id>
+    <title>Ford hires Elon Musk as CEO</title>
+    <updated>2019-04-01T07:30:00Z</updated>
+    <author><name>Entry Author</name></author>
+  </entry>
+  <entry>
+    <id>urn:uuid:9b1d4fc3-5a8e-4b0c-8c2e-2b7f1d3e6a10</id>
+    <title></title>
+    <updated>2019-04-02T07:30:00Z</updated>
+    <author><name>Entry Author</name></author>
+  </entry>
+  <entry>
+    <id>urn:uuid:0c2f6e8a-3d41-4f7b-9e5a-6b8c7d9e0f12</id>
+    <title>Ford stock rises</title>
+    <updated>2019-04-03T07:30:00Z</updated>
+  </entry>
+</feed>
+"#;
+
+    static ATOM_CATEGORIES_STR: &'static str = r#"
+<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <id>urn:uuid:b3420f84-6bdf-4f46-a225-f1b9a14703b6</id>
+  <title>TechCrunch</title>
+  <updated>2019-04-01T07:30:00Z</updated>
+  <entry>
+    <id>urn:uuid:4ae8550b-2987-49fa-9f8c-54c180c418ac</id>
+    <title>Ford hires Elon Musk as CEO</title>
+    <updated>2019-04-01T07:30:00Z</updated>
+    <category term="automotive" label="Automotive"/>
+    <category label="News"/>
+    <category>news</category>
+  </entry>
+</feed>
+"#;
+
+    static RSS_DUBLIN_CORE_STR: &'static str = r#"
+<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0" xmlns:dc="http://purl.org/dc/elements/1.1/">
+  <channel>
+    <title>TechCrunch</title>
+    <link>http://techcrunch.com</link>
+    <description>The latest technology news and information on startups</description>
+    <item>
+      <title>Ford hires Elon Musk as CEO</title>
+      <dc:date>2019-04-01T07:30:00Z</dc:date>
+      <dc:creator>Steven Sheldon</dc:creator>
+      <dc:subject>Automotive</dc:subject>
+    </item>
+  </channel>
+</rss>
+"#;
+
+    static RSS_COMMENTS_STR: &'static str = r#"
+<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0" xmlns:slash="http://purl.org/rss/1.0/modules/slash/">
+  <channel>
+    <title>TechCrunch</title>
+    <link>http://techcrunch.com</link>
+    <description>The latest technology news and information on startups</description>
+    <item>
+      <title>Ford hires Elon Musk as CEO</title>
+      <comments>http://techcrunch.com/ford-musk#comments</comments>
+      <slash:comments>42</slash:comments>
+    </item>
+  </channel>
+</rss>
+"#;
+
+    static RSS_TTL_STR: &'static str = r#"
+<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0">
+  <channel>
+    <title>TechCrunch</title>
+    <link>http://techcrunch.com</link>
+    <description>The latest technology news and information on startups</description>
+    <ttl>60</ttl>
+    <item>
+      <title>Ford hires Elon Musk as CEO</title>
+      <guid>http://techcrunch.com/2019/04/01/ford-elon-musk/</guid>
+      <pubDate>Mon, 01 Apr 2019 07:30:00 GMT</pubDate>
+    </item>
+  </channel>
+</rss>
+"#;
+
+    static RSS_MEDIA_STR: &'static str = r#"
+<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0" xmlns:media="http://search.yahoo.com/mrss/"
+    xmlns:itunes="http://www.itunes.com/dtds/podcast-1.0.dtd">
+  <channel>
+    <title>TechCrunch</title>
+    <link>http://techcrunch.com</link>
+    <description>The latest technology news and information on startups</description>
+    <item>
+      <title>Ford hires Elon Musk as CEO</title>
+      <media:content url="http://techcrunch.com/ford-musk.jpg" medium="image"/>
+      <media:thumbnail url="http://techcrunch.com/ford-musk-thumb.jpg"/>
+    </item>
+    <item>
+      <title>Elon Musk leaves Ford</title>
+      <media:content url="http://techcrunch.com/musk-leaves.mp4" type="video/mp4"/>
+      <media:content url="http://techcrunch.com/musk-leaves.png" type="image/png"/>
+    </item>
+    <item>
+      <title>Ford stock rises</title>
+      <media:group>
+        <media:thumbnail url="http://techcrunch.com/ford-stock.jpg"/>
+      </media:group>
+    </item>
+    <item>
+      <title>TechCrunch Podcast</title>
+      <itunes:image href="http://techcrunch.com/podcast.jpg"/>
+    </item>
+    <item>
+      <title>Ford makes cars</title>
+      <media:content url="http://techcrunch.com/cars.mp3" medium="audio"/>
+    </item>
+  </channel>
+</rss>
+"#;
+
+    static ATOM_ENCLOSURES_STR: &'static str = r#"
+<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <id>urn:uuid:b3420f84-6bdf-4f46-a225-f1b9a14703b6</id>
+  <title>TechCrunch</title>
+  <updated>2019-04-01T07:30:00Z</updated>
+  <entry>
+    <id>urn:uuid:4ae8550b-2987-49fa-9f8c-54c180c418ac</id>
+    <title>TechCrunch Podcast</title>
+    <updated>2019-04-01T07:30:00Z</updated>
+    <link href="http://techcrunch.com/podcast/1" type="text/html"/>
+    <link rel="enclosure" href="http://techcrunch.com/podcast/1.mp3" type="audio/mpeg" length="1234"/>
+    <link rel="enclosure" href="http://techcrunch.com/podcast/1.mp4" type="video/mp4"/>
+    <link rel="enclosure" href="http://techcrunch.com/podcast/1.m4a" type="Audio/MP4"/>
+  </entry>
+</feed>
+"#;
+
+    static RSS_ENCLOSURE_STR: &'static str = r#"
+<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0">
+  <channel>
+    <title>TechCrunch</title>
+    <link>http://techcrunch.com</link>
+    <description>The latest technology news and information on startups</description>
+    <item>
+      <title>TechCrunch Podcast</title>
+      <enclosure url="http://techcrunch.com/podcast/1.mp3" length="1234" type="audio/mpeg"/>
+    </item>
+  </channel>
+</rss>
+"#;
+
+    static ATOM_03_STR: &'static str = r#"
+<?xml version="1.0" encoding="utf-8"?>
+<feed version="0.3" xmlns="http://purl.org/atom/ns#">
+  <title>TechCrunch</title>
+  <tagline>The latest technology news and information on startups</tagline>
+  <link rel="alternate" type="text/html" href="http://techcrunch.com"/>
+  <modified>2019-04-02T07:30:00Z</modified>
+  <entry>
+    <title>Elon Musk leaves Ford</title>
+    <link rel="alternate" type="text/html" href="http://techcrunch.com/musk-leaves"/>
+    <id>tag:techcrunch.com,2019:musk-leaves</id>
+    <issued>2019-04-02T07:30:00Z</issued>
+    <modified>2019-04-02T07:30:00Z</modified>
+    <content type="text/html" mode="escaped">&lt;p&gt;Elon Musk has left Ford.&lt;/p&gt;</content>
+  </entry>
+  <entry>
+    <title>Ford hires Elon Musk as CEO</title>
+    <link rel="alternate" type="text/html" href="http://techcrunch.com/ford-musk"/>
+    <id>tag:techcrunch.com,2019:ford-musk</id>
+    <modified>2019-04-01T07:30:00Z</modified>
+  </entry>
+</feed>
+"#;
+
+    static ATOM_EMBEDDED_STR: &'static str = r#"
+<?xml version="1.0" encoding="utf-8"?>
+<soap:Envelope xmlns:soap="http://schemas.xmlsoap.org/soap/envelope/">
+  <soap:Header>
+    <title>Not the feed</title>
+  </soap:Header>
+  <soap:Body>
+    <feedback>Not the feed either</feedback>
+    <feed xmlns="http://www.w3.org/2005/Atom">
+      <id>urn:uuid:b3420f84-6bdf-4f46-a225-f1b9a14703b6</id>
+      <title>TechCrunch</title>
+      <updated>2019-04-01T07:30:00Z</updated>
+      <entry>
+        <id>urn:uuid:4ae8550b-2987-49fa-9f8c-54c180c418ac</id>
+        <title>Ford hires Elon Musk as CEO</title>
+        <updated>2019-04-01T07:30:00Z</updated>
+      </entry>
+    </feed>
+  </soap:Body>
+</soap:Envelope>
+"#;
+
+    static ATOM_REPLIES_STR: &'static str = r#"
+<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom" xmlns:thr="http://purl.org/syndication/thread/1.0">
+  <id>urn:uuid:b3420f84-6bdf-4f46-a225-f1b9a14703b6</id>
+  <title>TechCrunch</title>
+  <updated>2019-04-01T07:30:00Z</updated>
+  <entry>
+    <id>urn:uuid:4ae8550b-2987-49fa-9f8c-54c180c418ac</id>
+    <title>Ford hires Elon Musk as CEO</title>
+    <updated>2019-04-01T07:30:00Z</updated>
+    <link rel="replies" href="http://techcrunch.com/ford-musk/comments.atom" thr:count="7"/>
+    <thr:total>7</thr:total>
+  </entry>
+</feed>
+"#;
+
+    static ATOM_ARCHIVE_STR: &'static str = r#"
+<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <id>urn:uuid:b3420f84-6bdf-4f46-a225-f1b9a14703b6</id>
+  <title>TechCrunch</title>
+  <updated>2019-04-02T07:30:00Z</updated>
+  <link rel="self" href="http://techcrunch.com/feed/"/>
+  <link rel="prev-archive" href="/feed/archive/1"/>
+  <entry>
+    <id>urn:uuid:9b1d4fc3-5a8e-4b0c-8c2e-2b7f1d3e6a10</id>
+    <title>Elon Musk leaves Ford</title>
+    <updated>2019-04-02T07:30:00Z</updated>
+  </entry>
+</feed>
+"#;
+
+    static RSS_ENTITIES_STR: &'static str = r#"
+<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0">
+  <channel>
+    <title>TechCrunch</title>
+    <link>http://techcrunch.com</link>
+    <description>The latest technology news and information on startups</description>
+    <item>
+      <title>Ford &amp;mdash; Tesla &#8212; AT&amp;amp;T &amp;#8220;news&amp;#8221;</title>
+    </item>
+  </channel>
+</rss>
+"#;
+
+    static ATOM_ENTITIES_STR: &'static str = r#"
+<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <title>TechCrunch</title>
+  <entry>
+    <title>AT&amp;amp;T &amp;mdash; text</title>
+  </entry>
+  <entry>
+    <title type="html">AT&amp;amp;T &amp;mdash; html</title>
+  </entry>
+</feed>
+"#;
+
+    #[test]
+    fn test_rss_stream() {
+        let feed = Feed::parse(RSS_STR.as_bytes()).unwrap();
+        let mut entries = feed.entries();
+
+        let entry = entries.next().unwrap();
+        assert_eq!(entry.title, "Ford hires Elon Musk as CEO");
+        assert_eq!(entry.content, "In an unprecedented move, Ford hires Elon Musk.");
+        let expected_date = Utc.with_ymd_and_hms(2019, 4, 1, 7, 30, 0).unwrap();
+        assert_eq!(entry.published.unwrap(), expected_date);
+
+        assert!(entries.next().is_none());
+    }
+
+    #[test]
+    fn test_atom_stream() {
+        let feed = Feed::parse(ATOM_STR.as_bytes()).unwrap();
+        let mut entries = feed.entries();
+
+        let entry = entries.next().unwrap();
+        assert_eq!(entry.title, "Ford hires Elon Musk as CEO");
+        assert_eq!(entry.guid.unwrap(), "urn:uuid:4ae8550b-2987-49fa-9f8c-54c180c418ac");
+        let expected_date = Utc.with_ymd_and_hms(2019, 4, 1, 7, 30, 0).unwrap();
+        assert_eq!(entry.published.unwrap(), expected_date);
+
+        assert!(entries.next().is_none());
+    }
+
+    #[test]
+    fn test_atom_03() {
+        let feed = Feed::parse(ATOM_03_STR.as_bytes()).unwrap();
+        assert_eq!(feed.title(), "TechCrunch");
+        assert!(feed.subtitle().unwrap().starts_with("The latest technology news"));
+        assert_eq!(feed.site_url(), Some("http://techcrunch.com"));
+        let date = |day, hour| Utc.with_ymd_and_hms(2019, 4, day, hour, 30, 0).unwrap();
+        assert_eq!(feed.latest_updated().unwrap(), date(2, 7));
+
+        let entries: Vec<_> = feed.entries().collect();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].title, "Elon Musk leaves Ford");
+        assert_eq!(entries[0].link.as_deref(), Some("http://techcrunch.com/musk-leaves"));
+        assert_eq!(entries[0].content, "<p>Elon Musk has left Ford.</p>");
+        assert_eq!(entries[0].published.unwrap(), date(2, 7));
+        // Entries without an issued date fall back to when they were modified
+        assert_eq!(entries[1].published.unwrap(), date(1, 7));
+    }
+    #[test]
+    fn test_upgrade_atom_03() {
+        let upgraded = |source: &str| String::from_utf8(upgrade_atom_03(source.as_bytes()).into_owned()).unwrap();
+
+        // Prefixed elements keep their prefix
+        assert_eq!(
+            upgraded(r#"<a:feed xmlns:a="http://purl.org/atom/ns#"><a:modified>2019-04-02T07:30:00Z</a:modified><a:tagline/></a:feed>"#),
+            r#"<a:feed xmlns:a="http://purl.org/atom/ns#"><a:updated>2019-04-02T07:30:00Z</a:updated><a:subtitle/></a:feed>"#,
+        );
+
+        // Text, CDATA, and elements of other namespaces are left alone
+        let untouched = r#"<feed xmlns="http://purl.org/atom/ns#"><title><![CDATA[The <modified> tag]]></title><entry><content mode="xml"><div xmlns="http://www.w3.org/1999/xhtml"><modified>Not a date</modified></div></content><summary>&lt;issued&gt;</summary></entry></feed>"#;
+        assert_eq!(upgraded(untouched), untouched);
+
+        // Created is the published date unless the entry was also issued
+        assert_eq!(
+            upgraded(r#"<feed xmlns="http://purl.org/atom/ns#"><entry><created>2019-04-01T07:30:00Z</created></entry><entry><issued>2019-04-02T07:30:00Z</issued><created>2019-04-01T07:30:00Z</created></entry></feed>"#),
+            r#"<feed xmlns="http://purl.org/atom/ns#"><entry><published>2019-04-01T07:30:00Z</published></entry><entry><published>2019-04-02T07:30:00Z</published></entry></feed>"#,
+        );
+
+        let atom_10 = r#"<feed xmlns="http://www.w3.org/2005/Atom"><modified/></feed>"#;
+        assert!(matches!(upgrade_atom_03(atom_10.as_bytes()), Cow::Borrowed(_)));
+    }
+
+
+    #[test]
+    fn test_embedded_feed() {
+        let feed = Feed::parse(ATOM_EMBEDDED_STR.as_bytes()).unwrap();
+        assert_eq!(feed.title(), "TechCrunch");
+        let entry = feed.entries().next().unwrap();
+        assert_eq!(entry.title, "Ford hires Elon Musk as CEO");
+
+        // A feed element outside the Atom namespace isn't mistaken for one
+        let html = "<html><body><feed>TechCrunch</feed></body></html>";
+        assert!(Feed::parse(html.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_summary_content_fallback() {
+        let feed = Feed::parse(ATOM_SUMMARY_CONTENT_STR.as_bytes()).unwrap();
+        let mut entries = feed.entries();
+
+        let entry = entries.next().unwrap();
+        assert_eq!(entry.content, "In an unprecedented move, Ford hires Elon Musk.");
+        assert_eq!(entry.summary, "In an unprecedented move, Ford hires Elon Musk.");
+
+        let entry = entries.next().unwrap();
+        assert_eq!(entry.content, "<p>Elon Musk has left Ford.</p>");
+        assert_eq!(entry.summary, "<p>Elon Musk has left Ford.</p>");
+
+        assert!(entries.next().is_none());
+    }
+
+    #[test]
+    fn test_atom_comments() {
+        let feed = Feed::parse(ATOM_COMMENTS_STR.as_bytes()).unwrap();
+        assert_eq!(feed.title(), "TechCrunch");
+
+        let titles: Vec<_> = feed.entries().map(|entry| entry.title).collect();
+        assert_eq!(titles, ["Ford hires Elon Musk as CEO", "Elon Musk leaves Ford"]);
+    }
+
+    #[test]
+    fn test_validate_iris() {
+        let feed = Feed::parse(ATOM_STR.as_bytes()).unwrap();
+        assert!(feed.validate().is_empty());
+
+        let feed = Feed::parse(ATOM_INVALID_ICON_STR.as_bytes()).unwrap();
+        let warnings = feed.validate();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].element, "icon");
+    }
+
+    #[test]
+    fn test_resolve_relative_links() {
+        let feed = Feed::parse(ATOM_RELATIVE_STR.as_bytes()).unwrap();
+        let base_url = feed.base_url("https://techcrunch.com/feed/").unwrap();
+        let links: Vec<_> = feed.entries()
+            .map(|entry| entry.resolve_link(&base_url).unwrap().to_string())
+            .collect();
+        assert_eq!(links, ["http://techcrunch.com/post/1", "http://techcrunch.com/blog/post/2"]);
+
+        let feed = Feed::parse(ATOM_STR.as_bytes()).unwrap();
+        let base_url = feed.base_url("https://techcrunch.com/feed/").unwrap();
+        assert_eq!(base_url.as_str(), "https://techcrunch.com/feed/");
+    }
+
+    #[test]
+    fn test_generator() {
+        let feed = Feed::parse(br#"
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <title>TechCrunch</title>
+  <generator uri="https://wordpress.org/" version="5.1"> WordPress </generator>
+</feed>"#).unwrap();
+        assert_eq!(feed.generator(), Some("WordPress"));
+
+        for generator in ["<generator/>", r#"<generator uri="https://wordpress.org/" version="5.1"/>"#].iter() {
+            let source = format!(
+                r#"<feed xmlns="http://www.w3.org/2005/Atom"><title>TechCrunch</title>{}</feed>"#,
+                generator,
+            );
+            let feed = Feed::parse(source.as_bytes()).unwrap();
+            assert_eq!(feed.generator(), None);
+        }
+
+        let feed = Feed::parse(RSS_STR.as_bytes()).unwrap();
+        assert_eq!(feed.generator(), None);
+    }
+
+    #[test]
+    fn test_recoverable_errors() {
+        let truncated = &RSS_STR.as_bytes()[..RSS_STR.len() / 2];
+        let err = Feed::parse(truncated).err().unwrap();
+        assert!(err.is_recoverable(), "{:?}", err);
+
+        let truncated = &ATOM_STR.as_bytes()[..ATOM_STR.len() / 2];
+        let err = Feed::parse(truncated).err().unwrap();
+        assert!(matches!(err, FeedParseError::Atom(atom::Error::Eof)), "{:?}", err);
+
+        let err = Feed::parse(br#"<feed xmlns="http://www.w3.org/2005/Atom"><!-- trunc"#).err().unwrap();
+        assert!(err.is_recoverable(), "{:?}", err);
+
+        let err = Feed::parse(b"<html><body>Not found</body></html>").err().unwrap();
+        assert!(!err.is_recoverable(), "{:?}", err);
+
+        let err = Feed::parse(b"<rss><channel><title>TechCrunch</item></channel></rss>").err().unwrap();
+        assert!(!err.is_recoverable(), "{:?}", err);
+
+        let err = Feed::parse(br#"<feed xmlns="http://www.w3.org/2005/Atom"><title>TechCrunch</entry></feed>"#).err().unwrap();
+        assert!(!err.is_recoverable(), "{:?}", err);
+    }
+
+    #[test]
+    fn test_max_entries() {
+        let options = ParseOptions { max_entries: Some(2), ..Default::default() };
+        let feed = Feed::parse_with(RSS_DATES_STR.as_bytes(), &options).unwrap();
+        assert_eq!(feed.len(), 2);
+        assert_eq!(feed.title(), "TechCrunch");
+
+        let options = ParseOptions { max_entries: Some(0), ..Default::default() };
+        let feed = Feed::parse_with(ATOM_STR.as_bytes(), &options).unwrap();
+        assert!(feed.is_empty());
+        assert_eq!(feed.title(), "TechCrunch");
+
+        // Entries past the limit aren't read at all, so errors in them don't matter
+        let source = large_rss_feed(5).replace("\n  </channel>", "<item><title>Broken</item></channel>");
+        assert!(Feed::parse(source.as_bytes()).is_err());
+        let options = ParseOptions { max_entries: Some(2), ..Default::default() };
+        let feed = Feed::parse_with(source.as_bytes(), &options).unwrap();
+        let titles: Vec<_> = feed.entries().map(|entry| entry.title).collect();
+        assert_eq!(titles, ["Item 0", "Item 1"]);
+    }
 
     #[test]
-    fn test_atom_stream() {
+    fn test_duplicate_content() {
+        let source = ATOM_SUMMARY_CONTENT_STR.replacen("</content>",
+            "</content>\n    <content type=\"text\">Ford hires Tesla.</content>\n    <content/>", 1);
+
+        let options = ParseOptions::default();
+        let (feed, warnings) = Feed::parse_with_warnings(source.as_bytes(), &options).unwrap();
+        let entries: Vec<_> = feed.entries().collect();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].content, "<p>Elon Musk has left Ford.</p>");
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].element, "content");
+
+        let options = ParseOptions { strict: true, ..Default::default() };
+        match Feed::parse_with(source.as_bytes(), &options) {
+            Err(FeedParseError::DuplicateElement("content")) => (),
+            result => panic!("Expected a duplicate content error, got {:?}", result.err()),
+        }
+        assert!(Feed::parse_with(ATOM_SUMMARY_CONTENT_STR.as_bytes(), &options).is_ok());
+    }
+
+    #[test]
+    fn test_media_content_not_duplicate() {
+        let source = r#"
+<feed xmlns="http://www.w3.org/2005/Atom" xmlns:media="http://search.yahoo.com/mrss/">
+  <id>urn:uuid:b3420f84-6bdf-4f46-a225-f1b9a14703b6</id>
+  <title>TechCrunch</title>
+  <updated>2019-04-01T07:30:00Z</updated>
+  <entry>
+    <id>urn:uuid:4ae8550b-2987-49fa-9f8c-54c180c418ac</id>
+    <title>Ford hires Elon Musk as CEO</title>
+    <updated>2019-04-01T07:30:00Z</updated>
+    <media:content url="http://techcrunch.com/ford.mp4" medium="video"/>
+    <media:content url="http://techcrunch.com/ford.jpg" medium="image"></media:content>
+    <content type="html">&lt;p&gt;Ford hires Elon Musk.&lt;/p&gt;</content>
+  </entry>
+</feed>
+"#;
+        let options = ParseOptions::default();
+        let (feed, warnings) = Feed::parse_with_warnings(source.as_bytes(), &options).unwrap();
+        assert!(warnings.is_empty());
+        let entry = feed.entries().next().unwrap();
+        assert_eq!(entry.content, "<p>Ford hires Elon Musk.</p>");
+        assert_eq!(entry.thumbnail.as_deref(), Some("http://techcrunch.com/ford.jpg"));
+
+        let options = ParseOptions { strict: true, ..Default::default() };
+        assert!(Feed::parse_with(source.as_bytes(), &options).is_ok());
+    }
+
+    #[test]
+    fn test_lenient_dates() {
+        let expected_date = Utc.with_ymd_and_hms(2016, 9, 17, 19, 18, 32).unwrap();
+
+        let feed = Feed::parse(ATOM_RFC822_STR.as_bytes()).unwrap();
+        let entry = feed.entries().next().unwrap();
+        assert_eq!(entry.published.unwrap(), expected_date);
+
+        let feed = Feed::parse(RSS_RFC3339_STR.as_bytes()).unwrap();
+        for entry in feed.entries() {
+            assert_eq!(entry.published.unwrap(), expected_date);
+        }
+    }
+
+    #[test]
+    fn test_entries_since() {
+        let feed = Feed::parse(RSS_DATES_STR.as_bytes()).unwrap();
+        let since = Utc.with_ymd_and_hms(2019, 4, 1, 0, 0, 0).unwrap();
+        let titles: Vec<_> = feed.entries_since(since.into())
+            .into_iter()
+            .map(|entry| entry.title())
+            .collect();
+        assert_eq!(titles, ["Elon Musk leaves Ford", "Ford hires Elon Musk as CEO"]);
+    }
+
+    #[test]
+    fn test_entries_updated_since() {
+        let feed = Feed::parse(br#"
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <title>TechCrunch</title>
+  <entry>
+    <title>Ford hires Elon Musk as CEO</title>
+    <published>2019-03-31T07:30:00Z</published>
+    <updated>2019-04-02T07:30:00Z</updated>
+  </entry>
+  <entry>
+    <title>Ford stock rises</title>
+    <published>2019-03-30T07:30:00Z</published>
+    <updated>2019-03-31T07:30:00Z</updated>
+  </entry>
+</feed>"#).unwrap();
+        let since = Utc.with_ymd_and_hms(2019, 4, 1, 0, 0, 0).unwrap();
+        let titles: Vec<_> = feed.entries_since(since.into())
+            .into_iter()
+            .map(|entry| entry.title())
+            .collect();
+        assert_eq!(titles, ["Ford hires Elon Musk as CEO"]);
+    }
+
+    #[test]
+    fn test_parse_async() {
+        /// Records the thread that reads it, which is the one parsing.
+        struct Source(String, Arc<Mutex<Option<ThreadId>>>);
+
+        impl AsRef<[u8]> for Source {
+            fn as_ref(&self) -> &[u8] {
+                *self.1.lock().unwrap() = Some(thread::current().id());
+                self.0.as_bytes()
+            }
+        }
+
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let parsed_on = Arc::new(Mutex::new(None));
+        let source = Source(large_rss_feed(5000), parsed_on.clone());
+        let feed = rt.block_on(Feed::parse_async(source)).unwrap();
+        assert_eq!(feed.len(), 5000);
+        // The runtime's only thread is this one, so it was free while parsing
+        let parsed_on = parsed_on.lock().unwrap().unwrap();
+        assert_ne!(parsed_on, thread::current().id());
+    }
+
+    #[test]
+    fn test_author_inheritance() {
+        let feed = Feed::parse(ATOM_AUTHORS_STR.as_bytes()).unwrap();
+        let authors: Vec<_> = feed.entries()
+            .map(|entry| entry.author.unwrap())
+            .collect();
+        assert_eq!(authors, ["Entry Author", "Source Author", "Feed Author"]);
+    }
+
+    #[test]
+    fn test_subtitle() {
+        let feed = Feed::parse(RSS_STR.as_bytes()).unwrap();
+        assert_eq!(feed.subtitle(), Some("The latest technology news and information on startups"));
+
+        let feed = Feed::parse(ATOM_STR.as_bytes()).unwrap();
+        assert_eq!(feed.subtitle(), None);
+    }
+
+    #[test]
+    fn test_rss_content_encoded() {
+        let feed = Feed::parse(RSS_CONTENT_ENCODED_STR.as_bytes()).unwrap();
+        let entry = feed.entries().next().unwrap();
+        assert_eq!(entry.content, "<p>In an unprecedented move, Ford hires Elon Musk.</p>");
+        assert_eq!(entry.summary, "In an unprecedented move...");
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let feed = Feed::parse(ATOM_STR.as_bytes()).unwrap();
+        let same_feed = Feed::parse(ATOM_STR.as_bytes()).unwrap();
+        assert_eq!(feed.fingerprint(), same_feed.fingerprint());
+
+        let updated_str = ATOM_STR.replace(
+            "<updated>2019-04-01T07:30:00Z</updated>\n  </entry>",
+            "<updated>2019-04-02T07:30:00Z</updated>\n  </entry>",
+        );
+        assert_ne!(updated_str, ATOM_STR);
+        let updated_feed = Feed::parse(updated_str.as_bytes()).unwrap();
+        assert_ne!(feed.fingerprint(), updated_feed.fingerprint());
+    }
+
+    #[test]
+    fn test_categories() {
+        let feed = Feed::parse(RSS_CATEGORIES_STR.as_bytes()).unwrap();
+        let entry = feed.entries().next().unwrap();
+        assert_eq!(entry.categories.len(), 2);
+        assert_eq!(entry.categories[0].term, "Automotive");
+        assert_eq!(entry.categories[0].scheme, None);
+        assert_eq!(entry.categories[1].term, "People");
+        assert_eq!(entry.categories[1].scheme.as_deref(), Some("http://techcrunch.com/tags"));
+    }
+
+    #[test]
+    fn test_validate_atom() {
+        let feed = Feed::parse(ATOM_AUTHORS_STR.as_bytes()).unwrap();
+        assert!(feed.validate_atom().is_empty());
+
+        let feed = Feed::parse(ATOM_LINT_STR.as_bytes()).unwrap();
+        let elements: Vec<_> = feed.validate_atom().iter()
+            .map(|warning| warning.element)
+            .collect();
+        assert_eq!(elements, ["id", "title", "author"]);
+
+        let feed = Feed::parse(RSS_STR.as_bytes()).unwrap();
+        let warnings = feed.validate_atom();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].element, "rss");
+    }
+
+    #[test]
+    fn test_strip_query_params() {
+        let feed = Feed::parse(RSS_STR.as_bytes()).unwrap();
+        let mut entry = feed.entries().next().unwrap();
+        let params = ["utm_*", "fbclid"];
+
+        entry.link = Some("http://techcrunch.com/ford?id=1&utm_source=rss&utm_medium=feed".to_owned());
+        entry.strip_query_params(&params);
+        assert_eq!(entry.link.as_deref(), Some("http://techcrunch.com/ford?id=1"));
+
+        entry.link = Some("http://techcrunch.com/ford?utm_source=rss#comments".to_owned());
+        entry.strip_query_params(&params);
+        assert_eq!(entry.link.as_deref(), Some("http://techcrunch.com/ford#comments"));
+
+        entry.link = Some("http://techcrunch.com/ford?q=a+b".to_owned());
+        entry.strip_query_params(&params);
+        assert_eq!(entry.link.as_deref(), Some("http://techcrunch.com/ford?q=a+b"));
+    }
+
+    #[test]
+    fn test_atom_category_labels() {
+        let feed = Feed::parse(ATOM_CATEGORIES_STR.as_bytes()).unwrap();
+        let entry = feed.entries().next().unwrap();
+        let terms: Vec<_> = entry.categories.iter()
+            .map(|category| category.term.as_str())
+            .collect();
+        assert_eq!(terms, ["automotive", "News", "news"]);
+
+        // The term attribute is kept over any text
+        let source = ATOM_CATEGORIES_STR.replace("<category>news", r#"<category term="tech">news"#);
+        let feed = Feed::parse(source.as_bytes()).unwrap();
+        let entry = feed.entries().next().unwrap();
+        let terms: Vec<_> = entry.categories.iter()
+            .map(|category| category.term.as_str())
+            .collect();
+        assert_eq!(terms, ["automotive", "News", "tech"]);
+    }
+
+    #[test]
+    fn test_take_entries() {
+        let mut feed = Feed::parse(ATOM_AUTHORS_STR.as_bytes()).unwrap();
+        let entries = feed.take_entries();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[2].author.as_deref(), Some("Feed Author"));
+        assert!(feed.is_empty());
+        assert_eq!(feed.title(), "TechCrunch");
+
+        let mut feed = Feed::parse(RSS_STR.as_bytes()).unwrap();
+        let titles: Vec<_> = feed.drain_entries().map(|entry| entry.title).collect();
+        assert_eq!(titles, ["Ford hires Elon Musk as CEO"]);
+        assert!(feed.is_empty());
+    }
+
+    #[test]
+    fn test_take_entries_matches_entries() {
+        let sources = [
+            RSS_STR, RSS_ENTITIES_STR, RSS_DATES_STR, ATOM_STR, ATOM_SUMMARY_CONTENT_STR,
+            ATOM_AUTHORS_STR, ATOM_ENCLOSURES_STR, ATOM_ENTITIES_STR,
+        ];
+        for source in sources {
+            let mut feed = Feed::parse(source.as_bytes()).unwrap();
+            let expected: Vec<_> = feed.entries()
+                .map(|entry| (entry.title, entry.content, entry.summary, entry.links))
+                .collect();
+            let taken: Vec<_> = feed.take_entries().into_iter()
+                .map(|entry| (entry.title, entry.content, entry.summary, entry.links))
+                .collect();
+            assert_eq!(taken, expected);
+        }
+    }
+
+    #[test]
+    fn test_into_iter() {
+        let feed = Feed::parse(ATOM_AUTHORS_STR.as_bytes()).unwrap();
+        let mut authors = Vec::new();
+        for entry in &feed {
+            authors.push(entry.author());
+        }
+        assert_eq!(authors.len(), 3);
+        assert_eq!(authors[2], Some("Feed Author"));
+
+        let mut entries = Vec::new();
+        for entry in feed {
+            entries.push(entry);
+        }
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[2].author.as_deref(), Some("Feed Author"));
+
+        let feed = Feed::parse(RSS_STR.as_bytes()).unwrap();
+        let titles: Vec<_> = feed.into_iter().map(|entry| entry.title).collect();
+        assert_eq!(titles, ["Ford hires Elon Musk as CEO"]);
+    }
+
+    #[test]
+    fn test_dublin_core_fallbacks() {
+        let feed = Feed::parse(RSS_DUBLIN_CORE_STR.as_bytes()).unwrap();
+        let entry = feed.entries().next().unwrap();
+        assert_eq!(entry.published, Some(Utc.with_ymd_and_hms(2019, 4, 1, 7, 30, 0).unwrap().into()));
+        assert_eq!(entry.author.as_deref(), Some("Steven Sheldon"));
+        assert_eq!(entry.categories.len(), 1);
+        assert_eq!(entry.categories[0].term, "Automotive");
+
+        // The DC date is used when pubDate is there but can't be parsed
+        let source = RSS_DUBLIN_CORE_STR.replace("<dc:date>", "<pubDate>Soon</pubDate><dc:date>");
+        let feed = Feed::parse(source.as_bytes()).unwrap();
+        let entry = feed.entries().next().unwrap();
+        assert_eq!(entry.published, Some(Utc.with_ymd_and_hms(2019, 4, 1, 7, 30, 0).unwrap().into()));
+    }
+
+    #[test]
+    fn test_thumbnails() {
+        let feed = Feed::parse(RSS_MEDIA_STR.as_bytes()).unwrap();
+        let thumbnails: Vec<_> = feed.entries().map(|entry| entry.thumbnail).collect();
+        assert_eq!(thumbnails, [
+            Some("http://techcrunch.com/ford-musk-thumb.jpg".to_owned()),
+            Some("http://techcrunch.com/musk-leaves.png".to_owned()),
+            Some("http://techcrunch.com/ford-stock.jpg".to_owned()),
+            Some("http://techcrunch.com/podcast.jpg".to_owned()),
+            None,
+        ]);
+    }
+
+    #[test]
+    fn test_xhtml_summary() {
+        let xml = r#"
+<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <id>urn:uuid:b3420f84-6bdf-4f46-a225-f1b9a14703b6</id>
+  <title>TechCrunch</title>
+  <updated>2019-04-01T07:30:00Z</updated>
+  <entry>
+    <id>urn:uuid:4ae8550b-2987-49fa-9f8c-54c180c418ac</id>
+    <title>Ford hires Elon Musk as CEO</title>
+    <updated>2019-04-01T07:30:00Z</updated>
+    <summary type="xhtml"><div xmlns="http://www.w3.org/1999/xhtml"><p>Ford hires <b>Elon Musk</b> &amp; more</p></div></summary>
+  </entry>
+</feed>
+"#;
+        let markup = r#"<div xmlns="http://www.w3.org/1999/xhtml"><p>Ford hires <b>Elon Musk</b> &amp; more</p></div>"#;
+
+        let feed = Feed::parse(xml.as_bytes()).unwrap();
+        let entry = feed.entries().next().unwrap();
+        assert_eq!(entry.summary, markup);
+
+        let Feed::Atom(atom_feed) = feed else { unreachable!() };
+        let output = atom_feed.to_string();
+        assert!(output.contains(&format!(r#"<summary type="xhtml">{}</summary>"#, markup)));
+        let reparsed = Feed::parse(output.as_bytes()).unwrap();
+        assert_eq!(reparsed.entries().next().unwrap().summary, markup);
+    }
+
+    #[test]
+    fn test_via_related_links() {
+        let xml = r#"
+<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <id>urn:uuid:b3420f84-6bdf-4f46-a225-f1b9a14703b6</id>
+  <title>TechCrunch</title>
+  <updated>2019-04-01T07:30:00Z</updated>
+  <entry>
+    <id>urn:uuid:9a6fc5ab-3b5b-4d4b-8a7a-2c8e0b0e1b6a</id>
+    <title>Ford hires Elon Musk as CEO</title>
+    <updated>2019-04-01T07:30:00Z</updated>
+    <link href="http://techcrunch.com/ford-musk"/>
+    <link rel="related" href="http://techcrunch.com/ford"/>
+    <link rel="via" href="http://reuters.com/ford-musk"/>
+    <link rel="related" href="http://techcrunch.com/tesla"/>
+  </entry>
+</feed>"#;
+        let feed = Feed::parse(xml.as_bytes()).unwrap();
+        let entry = feed.entries().next().unwrap();
+        assert_eq!(entry.via_link().unwrap().href(), "http://reuters.com/ford-musk");
+        let related: Vec<_> = entry.related_links().map(|link| link.href()).collect();
+        assert_eq!(related, ["http://techcrunch.com/ford", "http://techcrunch.com/tesla"]);
+
         let feed = Feed::parse(ATOM_STR.as_bytes()).unwrap();
+        let entry = feed.entries().next().unwrap();
+        assert!(entry.via_link().is_none());
+        assert_eq!(entry.related_links().count(), 0);
+    }
+
+    #[test]
+    fn test_links_of_type() {
+        let feed = Feed::parse(ATOM_ENCLOSURES_STR.as_bytes()).unwrap();
+        let entry = feed.entries().next().unwrap();
+        assert_eq!(entry.enclosures.len(), 3);
+
+        let audio: Vec<_> = entry.links_of_type("audio/").map(|link| link.href()).collect();
+        assert_eq!(audio, [
+            "http://techcrunch.com/podcast/1.mp3",
+            "http://techcrunch.com/podcast/1.m4a",
+        ]);
+        assert_eq!(entry.links_of_type("audio/mpeg").next().unwrap().length(), Some("1234"));
+        // The alternate link isn't an enclosure
+        assert_eq!(entry.links_of_type("text/").count(), 0);
+
+        let feed = Feed::parse(RSS_ENCLOSURE_STR.as_bytes()).unwrap();
+        let entry = feed.entries().next().unwrap();
+        let audio: Vec<_> = entry.links_of_type("audio/").map(|link| link.href()).collect();
+        assert_eq!(audio, ["http://techcrunch.com/podcast/1.mp3"]);
+        assert_eq!(entry.links_of_type("video/").count(), 0);
+    }
+
+    #[test]
+    fn test_link_attributes() {
+        let feed = Feed::parse(r#"
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <title>TechCrunch</title>
+  <link rel="self" href="http://techcrunch.com/feed/" type="application/atom+xml"/>
+  <entry>
+    <title>Ford hires Elon Musk as CEO</title>
+    <link href="http://techcrunch.com/ford-musk" type="text/html" hreflang="en" title="In English"/>
+    <link href="http://techcrunch.com/fr/ford-musk" type="text/html" hreflang="fr" title="En français"/>
+  </entry>
+</feed>"#.as_bytes()).unwrap();
+        assert!(feed.links()[0].is_self());
+
+        let entry = feed.entries().next().unwrap();
+        let links: Vec<_> = entry.links.iter()
+            .map(|link| (link.rel(), link.href(), link.mime_type(), link.hreflang(), link.title()))
+            .collect();
+        assert_eq!(links, [
+            ("alternate", "http://techcrunch.com/ford-musk", Some("text/html"), Some("en"), Some("In English")),
+            ("alternate", "http://techcrunch.com/fr/ford-musk", Some("text/html"), Some("fr"), Some("En français")),
+        ]);
+
+        let feed = Feed::parse(RSS_ENCLOSURE_STR.as_bytes()).unwrap();
+        let links: Vec<_> = feed.links().iter().map(|link| link.href().to_owned()).collect();
+        assert_eq!(links, ["http://techcrunch.com"]);
+        let entry = feed.entries().next().unwrap();
+        let rels: Vec<_> = entry.links.iter().map(|link| link.rel()).collect();
+        assert_eq!(rels, ["enclosure"]);
+    }
+
+    #[test]
+    fn test_dedup_links() {
+        let xml = ATOM_ENCLOSURES_STR.replace(
+            "<link href=\"http://techcrunch.com/podcast/1\" type=\"text/html\"/>",
+            "<link href=\"http://techcrunch.com/podcast/1\" type=\"text/html\"/>
+    <link rel=\"alternate\" href=\"http://techcrunch.com/podcast/1\" type=\"text/html\"/>
+    <link rel=\"enclosure\" href=\"http://techcrunch.com/podcast/1.mp4\" type=\"video/mp4\"/>",
+        );
+        let mut feed = Feed::parse(xml.as_bytes()).unwrap();
+        let entry_links = |feed: &Feed| match feed {
+            Feed::Atom(feed) => feed.entries()[0].links().len(),
+            Feed::Rss(_) => unreachable!(),
+        };
+        assert_eq!(entry_links(&feed), 6);
+        assert_eq!(feed.entries().next().unwrap().enclosures.len(), 4);
+
+        feed.dedup_links();
+        assert_eq!(entry_links(&feed), 4);
+        let mut entry = feed.entries().next().unwrap();
+        assert_eq!(entry.enclosures.len(), 3);
+
+        entry.enclosures.push(entry.enclosures[0].clone());
+        entry.dedup_links();
+        assert_eq!(entry.enclosures.len(), 3);
+    }
+
+    #[test]
+    fn test_reading_time() {
+        let feed = Feed::parse(RSS_CONTENT_ENCODED_STR.as_bytes()).unwrap();
+        let mut entry = feed.entries().next().unwrap();
+        assert_eq!(entry.word_count(), 8);
+        assert_eq!(entry.reading_time(240), Some(Duration::from_secs(2)));
+
+        entry.content = "<p>Ford&nbsp;hires<br/>Elon&#160;Musk</p> &mdash; <img src=\"ford.jpg\">".to_owned();
+        assert_eq!(entry.word_count(), 4);
+        assert_eq!(entry.reading_time(200), Some(Duration::from_millis(1200)));
+
+        entry.content = "<p> </p>".to_owned();
+        assert_eq!(entry.reading_time(200), None);
+    }
+
+    #[test]
+    fn test_summary_text() {
+        let feed = Feed::parse(RSS_CONTENT_ENCODED_STR.as_bytes()).unwrap();
+        let mut entry = feed.entries().next().unwrap();
+
+        entry.summary = "<p>Ford&nbsp;hires <b>Elon\n  Musk</b>&amp; <i>more</i></p>".to_owned();
+        assert_eq!(entry.summary_text(None).as_deref(), Some("Ford hires Elon Musk & more"));
+
+        // Text right at the limit is kept whole, and anything longer
+        // is cut to the limit including the ellipsis
+        assert_eq!(entry.summary_text(Some(27)).as_deref(), Some("Ford hires Elon Musk & more"));
+        assert_eq!(entry.summary_text(Some(26)).as_deref(), Some("Ford hires Elon Musk & mo…"));
+        assert_eq!(entry.summary_text(Some(12)).as_deref(), Some("Ford hires…"));
+
+        entry.summary = "<p> </p>".to_owned();
+        entry.content = "<p>Elon Musk has left Ford.</p>".to_owned();
+        assert_eq!(entry.summary_text(None).as_deref(), Some("Elon Musk has left Ford."));
+
+        entry.content = "<img src=\"ford.jpg\">".to_owned();
+        assert_eq!(entry.summary_text(Some(10)), None);
+    }
+
+    #[test]
+    fn test_guid_is_permalink() {
+        let xml = r#"
+<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0">
+  <channel>
+    <title>TechCrunch</title>
+    <link>http://techcrunch.com</link>
+    <description>The latest technology news and information on startups</description>
+    <item>
+      <title>Ford hires Elon Musk as CEO</title>
+      <guid isPermaLink="true">http://techcrunch.com/2019/04/01/ford-elon-musk/</guid>
+    </item>
+    <item>
+      <title>Elon Musk leaves Ford</title>
+      <guid isPermaLink="false">urn:uuid:9b1d4fc3-5a8e-4b0c-8c2e-2b7f1d3e6a10</guid>
+    </item>
+  </channel>
+</rss>
+"#;
+        let feed = Feed::parse(xml.as_bytes()).unwrap();
         let mut entries = feed.entries();
 
         let entry = entries.next().unwrap();
-        assert_eq!(entry.title, "Ford hires Elon Musk as CEO");
-        assert_eq!(entry.guid.unwrap(), "urn:uuid:4ae8550b-2987-49fa-9f8c-54c180c418ac");
-        let expected_date = Utc.with_ymd_and_hms(2019, 4, 1, 7, 30, 0).unwrap();
-        assert_eq!(entry.published.unwrap(), expected_date);
+        assert!(entry.guid_is_permalink);
+        assert_eq!(entry.link.as_deref(), Some("http://techcrunch.com/2019/04/01/ford-elon-musk/"));
 
-        assert!(entries.next().is_none());
+        let entry = entries.next().unwrap();
+        assert!(!entry.guid_is_permalink);
+        assert_eq!(entry.guid.as_deref(), Some("urn:uuid:9b1d4fc3-5a8e-4b0c-8c2e-2b7f1d3e6a10"));
+        assert_eq!(entry.link, None);
+
+        let feed = Feed::parse(ATOM_STR.as_bytes()).unwrap();
+        assert!(!feed.entries().next().unwrap().guid_is_permalink);
+    }
+
+    #[test]
+    fn test_update_interval() {
+        let feed = Feed::parse(RSS_TTL_STR.as_bytes()).unwrap();
+        assert_eq!(feed.update_interval(), Some(Duration::from_secs(60 * 60)));
+
+        let xml = r#"
+<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom" xmlns:sy="http://purl.org/rss/1.0/modules/syndication/">
+  <id>urn:uuid:b3420f84-6bdf-4f46-a225-f1b9a14703b6</id>
+  <title>TechCrunch</title>
+  <updated>2019-04-01T07:30:00Z</updated>
+  <sy:updatePeriod>daily</sy:updatePeriod>
+  <sy:updateFrequency>4</sy:updateFrequency>
+</feed>
+"#;
+        let feed = Feed::parse(xml.as_bytes()).unwrap();
+        assert_eq!(feed.update_interval(), Some(Duration::from_secs(6 * 60 * 60)));
+
+        let feed = Feed::parse(RSS_COMMENTS_STR.as_bytes()).unwrap();
+        assert_eq!(feed.update_interval(), None);
+    }
+
+    #[test]
+    fn test_latest_updated() {
+        let xml = r#"
+<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <id>urn:uuid:b3420f84-6bdf-4f46-a225-f1b9a14703b6</id>
+  <title>TechCrunch</title>
+  <updated>2019-04-01T07:30:00Z</updated>
+  <entry>
+    <id>urn:uuid:4ae8550b-2987-49fa-9f8c-54c180c418ac</id>
+    <title>Elon Musk leaves Ford</title>
+    <published>2019-04-01T07:30:00Z</published>
+    <updated>2019-04-02T09:00:00Z</updated>
+  </entry>
+</feed>
+"#;
+        let feed = Feed::parse(xml.as_bytes()).unwrap();
+        let expected = Utc.with_ymd_and_hms(2019, 4, 2, 9, 0, 0).unwrap();
+        assert_eq!(feed.latest_updated(), Some(expected.into()));
+
+        let xml = r#"
+<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0">
+  <channel>
+    <title>TechCrunch</title>
+    <link>http://techcrunch.com</link>
+    <description>The latest technology news and information on startups</description>
+    <lastBuildDate>Mon, 01 Apr 2019 07:30:00 GMT</lastBuildDate>
+    <item>
+      <title>Ford hires Elon Musk as CEO</title>
+      <pubDate>Sometime in April</pubDate>
+    </item>
+  </channel>
+</rss>
+"#;
+        let feed = Feed::parse(xml.as_bytes()).unwrap();
+        let expected = Utc.with_ymd_and_hms(2019, 4, 1, 7, 30, 0).unwrap();
+        assert_eq!(feed.latest_updated(), Some(expected.into()));
+
+        let feed = Feed::parse(RSS_COMMENTS_STR.as_bytes()).unwrap();
+        assert_eq!(feed.latest_updated(), None);
+    }
+
+    #[test]
+    fn test_comments() {
+        let feed = Feed::parse(RSS_COMMENTS_STR.as_bytes()).unwrap();
+        let entry = feed.entries().next().unwrap();
+        assert_eq!(entry.comments_url.as_deref(), Some("http://techcrunch.com/ford-musk#comments"));
+        assert_eq!(entry.comments_count, Some(42));
+
+        let feed = Feed::parse(ATOM_REPLIES_STR.as_bytes()).unwrap();
+        let entry = feed.entries().next().unwrap();
+        assert_eq!(entry.comments_url.as_deref(), Some("http://techcrunch.com/ford-musk/comments.atom"));
+        assert_eq!(entry.comments_count, Some(7));
+
+        let feed = Feed::parse(RSS_STR.as_bytes()).unwrap();
+        let entry = feed.entries().next().unwrap();
+        assert_eq!(entry.comments_url, None);
+        assert_eq!(entry.comments_count, None);
+    }
+
+    #[test]
+    fn test_archive_url() {
+        let feed = Feed::parse(ATOM_ARCHIVE_STR.as_bytes()).unwrap();
+        assert_eq!(feed.archive_url(), Some("/feed/archive/1"));
+
+        let feed = Feed::parse(ATOM_STR.as_bytes()).unwrap();
+        assert_eq!(feed.archive_url(), None);
+    }
+
+    #[test]
+    fn test_entry_refs_borrow() {
+        let feed = Feed::parse(ATOM_STR.as_bytes()).unwrap();
+        let atom_entry = match &feed {
+            Feed::Atom(atom_feed) => &atom_feed.entries()[0],
+            Feed::Rss(_) => unreachable!(),
+        };
+
+        let entry = feed.entry_refs().next().unwrap();
+        assert_eq!(entry.title(), "Ford hires Elon Musk as CEO");
+        assert!(std::ptr::eq(&*entry.title(), atom_entry.title().as_str()));
+        assert!(std::ptr::eq(entry.guid().unwrap(), atom_entry.id()));
+    }
+
+    #[test]
+    fn test_title_entities() {
+        let feed = Feed::parse(RSS_ENTITIES_STR.as_bytes()).unwrap();
+        let entry = feed.entries().next().unwrap();
+        assert_eq!(entry.title, "Ford \u{2014} Tesla \u{2014} AT&T \u{201c}news\u{201d}");
+    }
+
+    #[test]
+    fn test_atom_title_entities() {
+        let feed = Feed::parse(ATOM_ENTITIES_STR.as_bytes()).unwrap();
+        let titles: Vec<_> = feed.entries().map(|entry| entry.title).collect();
+        assert_eq!(titles, vec!["AT&amp;T &mdash; text", "AT&T \u{2014} html"]);
     }
 }
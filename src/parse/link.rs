@@ -0,0 +1,172 @@
+use std::collections::HashSet;
+
+use atom_syndication::Link;
+
+static IANA_RELATION_PREFIX: &str = "http://www.iana.org/assignments/relation/";
+
+pub trait LinkExt {
+    /// Returns the rel of this link, treating a missing rel as "alternate"
+    /// and registered relation IRIs as their short names, per RFC 4287.
+    fn effective_rel(&self) -> &str;
+
+    fn rel_is(&self, rel: &str) -> bool {
+        self.effective_rel().eq_ignore_ascii_case(rel)
+    }
+
+    /// Returns whether this links to an alternate version of its context,
+    /// which links without a rel do.
+    fn is_alternate(&self) -> bool {
+        self.rel_is("alternate")
+    }
+
+    fn is_self(&self) -> bool {
+        self.rel_is("self")
+    }
+
+    fn is_enclosure(&self) -> bool {
+        self.rel_is("enclosure")
+    }
+
+    fn is_related(&self) -> bool {
+        self.rel_is("related")
+    }
+
+    fn is_via(&self) -> bool {
+        self.rel_is("via")
+    }
+}
+
+impl LinkExt for Link {
+    fn effective_rel(&self) -> &str {
+        let rel = self.rel().trim();
+        let rel = rel.strip_prefix(IANA_RELATION_PREFIX).unwrap_or(rel);
+        if rel.is_empty() { "alternate" } else { rel }
+    }
+}
+
+/// Finds the alternate link, or the first link if none are alternate.
+pub fn find_alternate(links: &[Link]) -> Option<&Link> {
+    links.iter()
+        .find(|link| link.is_alternate())
+        .or(links.first())
+}
+
+/// Finds the via link, which identifies the source of the information.
+pub fn via_link(links: &[Link]) -> Option<&Link> {
+    links.iter().find(|link| link.is_via())
+}
+
+/// Returns the links to related resources.
+pub fn related_links(links: &[Link]) -> impl Iterator<Item=&Link> {
+    links.iter().filter(|link| link.is_related())
+}
+
+/// Returns the links whose media type starts with the given prefix,
+/// like "audio/" for all audio, ignoring case as MIME types do.
+pub fn links_of_type<'a>(links: &'a [Link], mime_prefix: &'a str)
+-> impl Iterator<Item=&'a Link> {
+    links.iter().filter(move |link| {
+        let mime_type = link.mime_type().map(str::trim).unwrap_or("");
+        mime_type.get(..mime_prefix.len())
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case(mime_prefix))
+    })
+}
+
+/// Removes links with the same href, effective rel, and type
+/// as an earlier link, keeping the order of the rest.
+pub fn dedup_links(links: &mut Vec<Link>) {
+    let mut seen = HashSet::new();
+    links.retain(|link| {
+        let key = (link.href.clone(), link.effective_rel().to_owned(), link.mime_type.clone());
+        seen.insert(key)
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use atom_syndication::Link;
+    use super::{LinkExt, dedup_links, find_alternate, related_links, via_link};
+
+    fn link(href: &str, rel: &str) -> Link {
+        let mut link = Link::default();
+        link.set_href(href);
+        link.set_rel(rel);
+        link
+    }
+
+    #[test]
+    fn test_effective_rel() {
+        assert_eq!(link("http://techcrunch.com", "").effective_rel(), "alternate");
+        assert_eq!(link("http://techcrunch.com", "self").effective_rel(), "self");
+        let iana_link = link(
+            "http://techcrunch.com",
+            "http://www.iana.org/assignments/relation/alternate",
+        );
+        assert!(iana_link.rel_is("alternate"));
+    }
+
+    #[test]
+    fn test_rel_predicates() {
+        let no_rel = link("http://techcrunch.com", "");
+        assert!(no_rel.is_alternate());
+        assert!(!no_rel.is_self() && !no_rel.is_enclosure() && !no_rel.is_related() && !no_rel.is_via());
+
+        assert!(link("http://techcrunch.com", "alternate").is_alternate());
+        assert!(link("http://techcrunch.com/feed/", "self").is_self());
+        assert!(link("http://techcrunch.com/ford.mp3", "enclosure").is_enclosure());
+        assert!(link("http://techcrunch.com/tesla", "related").is_related());
+        assert!(link("http://theverge.com", "via").is_via());
+        assert!(link("http://techcrunch.com/feed/", "SELF").is_self());
+        assert!(link("http://techcrunch.com/feed/", "http://www.iana.org/assignments/relation/self").is_self());
+        assert!(!link("http://techcrunch.com/feed/", "self").is_alternate());
+    }
+
+    #[test]
+    fn test_find_alternate() {
+        let links = [
+            link("http://techcrunch.com/feed/", "self"),
+            link("http://techcrunch.com", ""),
+        ];
+        let alternate = find_alternate(&links).unwrap();
+        assert_eq!(alternate.href(), "http://techcrunch.com");
+    }
+
+    #[test]
+    fn test_via_related() {
+        let links = [
+            link("http://techcrunch.com/ford-musk", ""),
+            link("http://example.com/ford", "related"),
+            link("http://reuters.com/ford-musk", "via"),
+            link("http://example.com/tesla", "http://www.iana.org/assignments/relation/related"),
+        ];
+        assert_eq!(via_link(&links).unwrap().href(), "http://reuters.com/ford-musk");
+
+        let related: Vec<_> = related_links(&links).map(|link| link.href()).collect();
+        assert_eq!(related, ["http://example.com/ford", "http://example.com/tesla"]);
+
+        assert!(via_link(&links[..2]).is_none());
+    }
+
+    #[test]
+    fn test_dedup_links() {
+        let mut enclosure = link("http://techcrunch.com/podcast.mp3", "enclosure");
+        enclosure.set_mime_type("audio/mpeg".to_owned());
+        let mut links = vec![
+            link("http://techcrunch.com/ford-musk", ""),
+            enclosure.clone(),
+            link("http://techcrunch.com/ford-musk", "alternate"),
+            link("http://techcrunch.com/ford-musk", "related"),
+            enclosure,
+            link("http://techcrunch.com/podcast.mp3", "enclosure"),
+        ];
+        dedup_links(&mut links);
+
+        let hrefs: Vec<_> = links.iter().map(|link| (link.href(), link.rel())).collect();
+        assert_eq!(hrefs, [
+            ("http://techcrunch.com/ford-musk", ""),
+            ("http://techcrunch.com/podcast.mp3", "enclosure"),
+            ("http://techcrunch.com/ford-musk", "related"),
+            ("http://techcrunch.com/podcast.mp3", "enclosure"),
+        ]);
+    }
+}
@@ -1,5 +1,18 @@
+mod convert;
+mod entities;
 mod entry;
 mod feed;
+mod link;
+mod paginate;
+mod person;
+mod validate;
+mod visit;
 
-pub use entry::Entry;
-pub use feed::{Feed, FeedParseError};
+pub(crate) use entities::decode_entities;
+pub use entry::{Category, Entry, EntryRef, effective_authors, preview_text};
+pub use feed::{DrainEntries, Entries, Feed, FeedParseError, IntoEntries, ParseOptions};
+pub use paginate::{PageError, paginate_entries};
+pub use link::{LinkExt, dedup_links, find_alternate, links_of_type, related_links, via_link};
+pub use person::PersonExt;
+pub use validate::Warning;
+pub use visit::Visitor;
@@ -0,0 +1,134 @@
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
+use std::future::Future;
+
+use futures::stream::{self, Stream};
+
+use super::entry::Entry;
+use super::feed::{Feed, FeedParseError, IntoEntries};
+
+#[derive(Debug)]
+pub enum PageError<E> {
+    Fetch(E),
+    Parse(FeedParseError),
+}
+
+impl<E: fmt::Display> fmt::Display for PageError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PageError::Fetch(err) => fmt::Display::fmt(err, f),
+            PageError::Parse(err) => fmt::Display::fmt(err, f),
+        }
+    }
+}
+
+impl<E: Error + 'static> Error for PageError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PageError::Fetch(err) => Some(err),
+            PageError::Parse(err) => Some(err),
+        }
+    }
+}
+
+struct Pages<F> {
+    fetch: F,
+    next_url: Option<String>,
+    seen: HashSet<String>,
+    entries: Option<IntoEntries>,
+}
+
+/// Streams the entries of a paged or archived feed, starting from the given
+/// url and following the links to older pages, per RFC 5005.
+/// Each page is only fetched once the entries before it have been taken.
+/// The stream ends after an error or when a page links back to one
+/// that was already seen.
+pub fn paginate_entries<F, Fut, B, E>(first_url: &str, fetch: F)
+-> impl Stream<Item = Result<Entry, PageError<E>>>
+where F: FnMut(String) -> Fut,
+      Fut: Future<Output = Result<B, E>>,
+      B: AsRef<[u8]> + Send + 'static {
+    let pages = Pages {
+        fetch,
+        next_url: Some(first_url.to_owned()),
+        seen: HashSet::new(),
+        entries: None,
+    };
+    stream::unfold(pages, |mut pages| async move {
+        loop {
+            if let Some(entry) = pages.entries.as_mut().and_then(Iterator::next) {
+                return Some((Ok(entry), pages));
+            }
+
+            let url = pages.next_url.take()
+                .filter(|url| pages.seen.insert(url.clone()))?;
+            let page = match (pages.fetch)(url.clone()).await {
+                Ok(body) => Feed::parse_async(body).await.map_err(PageError::Parse),
+                Err(err) => Err(PageError::Fetch(err)),
+            };
+            match page {
+                Ok(page) => {
+                    pages.next_url = page.resolved_archive_url(&url).map(String::from);
+                    pages.entries = Some(page.into_iter());
+                }
+                Err(err) => return Some((Err(err), pages)),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use futures::StreamExt;
+    use super::paginate_entries;
+
+    fn page(titles: &[&str], next_url: Option<&str>) -> String {
+        let next_link = next_url
+            .map(|url| format!(r#"<link rel="next" href="{}"/>"#, url))
+            .unwrap_or_default();
+        let entries: String = titles.iter()
+            .map(|title| format!("<entry><id>urn:{}</id><title>{}</title></entry>", title, title))
+            .collect();
+        format!(r#"<feed xmlns="http://www.w3.org/2005/Atom"><title>TechCrunch</title>{}{}</feed>"#,
+            next_link, entries)
+    }
+
+    #[test]
+    fn test_paginate_entries() {
+        let pages = HashMap::from([
+            ("http://techcrunch.com/feed/", page(&["Ford hires Elon Musk", "Tesla"], Some("/feed/?page=2"))),
+            ("http://techcrunch.com/feed/?page=2", page(&["SpaceX"], Some("/feed/"))),
+        ]);
+        let mut fetched = Vec::new();
+        let fetch = |url: String| {
+            fetched.push(url.clone());
+            let body = pages.get(url.as_str()).cloned().ok_or("not found");
+            async move { body }
+        };
+
+        let rt = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let titles: Vec<_> = rt.block_on(async {
+            let entries = paginate_entries("http://techcrunch.com/feed/", fetch);
+            entries.map(|entry| entry.unwrap().title).collect().await
+        });
+        assert_eq!(titles, ["Ford hires Elon Musk", "Tesla", "SpaceX"]);
+        // The link back to the first page isn't followed
+        assert_eq!(fetched, ["http://techcrunch.com/feed/", "http://techcrunch.com/feed/?page=2"]);
+    }
+
+    #[test]
+    fn test_paginate_entries_error() {
+        let fetch = |url: String| async move {
+            if url.ends_with("page=2") { Err("not found") } else { Ok(page(&["Tesla"], Some("?page=2"))) }
+        };
+
+        let rt = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let results: Vec<_> = rt.block_on(paginate_entries("http://techcrunch.com/feed/", fetch).collect());
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].as_ref().unwrap().title, "Tesla");
+        assert!(results[1].is_err());
+    }
+}
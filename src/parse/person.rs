@@ -0,0 +1,35 @@
+use atom_syndication::Person;
+use md5::{Digest, Md5};
+
+use crate::hash::md5_hex;
+
+pub trait PersonExt {
+    /// Returns the url of the Gravatar for this person's email, if they have one.
+    fn gravatar_url(&self, size: u32) -> Option<String>;
+}
+
+impl PersonExt for Person {
+    fn gravatar_url(&self, size: u32) -> Option<String> {
+        let email = self.email()?.trim().to_lowercase();
+        let hash = md5_hex(Md5::new_with_prefix(email.as_bytes()));
+        Some(format!("https://www.gravatar.com/avatar/{}?s={}", hash, size))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use atom_syndication::Person;
+    use super::PersonExt;
+
+    #[test]
+    fn test_gravatar_url() {
+        let mut person = Person::default();
+        assert_eq!(person.gravatar_url(80), None);
+
+        person.set_email(" MyEmailAddress@example.com ".to_owned());
+        assert_eq!(
+            person.gravatar_url(80).unwrap(),
+            "https://www.gravatar.com/avatar/0bc83cb571cd1c50ba6f3e8a78ef1346?s=80",
+        );
+    }
+}
@@ -0,0 +1,56 @@
+use std::fmt;
+
+use url::{ParseError, Url};
+
+/// A non-fatal problem found while validating a parsed feed.
+#[derive(Debug, PartialEq)]
+pub struct Warning {
+    pub element: &'static str,
+    pub message: String,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<{}>: {}", self.element, self.message)
+    }
+}
+
+fn iri_error(value: &str) -> Option<ParseError> {
+    match Url::parse(value) {
+        Ok(_) => None,
+        // Relative references are allowed, so check them against a dummy base
+        Err(ParseError::RelativeUrlWithoutBase) => {
+            let base = Url::parse("http://example.invalid/").unwrap();
+            base.join(value).err()
+        }
+        Err(err) => Some(err),
+    }
+}
+
+pub fn check_iri(element: &'static str, value: &str, warnings: &mut Vec<Warning>) {
+    if let Some(err) = iri_error(value.trim()) {
+        warnings.push(Warning {
+            element,
+            message: format!("{:?} is not a valid IRI ({})", value, err),
+        });
+    }
+}
+
+/// Checks that an id is an absolute IRI, as Atom requires.
+pub fn check_absolute_iri(element: &'static str, value: &str, warnings: &mut Vec<Warning>) {
+    if let Err(ParseError::RelativeUrlWithoutBase) = Url::parse(value.trim()) {
+        warnings.push(Warning {
+            element,
+            message: format!("{:?} is a relative reference, not an absolute IRI", value),
+        });
+    }
+}
+
+pub fn check_not_empty(element: &'static str, value: &str, warnings: &mut Vec<Warning>) {
+    if value.trim().is_empty() {
+        warnings.push(Warning {
+            element,
+            message: "is required but empty".to_owned(),
+        });
+    }
+}
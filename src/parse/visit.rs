@@ -0,0 +1,133 @@
+use atom_syndication::{Category, Entry, Feed, Link, Person, Source};
+
+/// Callbacks for each person, link, and category in a feed,
+/// which are all no-ops unless overridden.
+pub trait Visitor {
+    fn visit_person(&mut self, _person: &Person) { }
+
+    fn visit_link(&mut self, _link: &Link) { }
+
+    fn visit_category(&mut self, _category: &Category) { }
+}
+
+fn visit_all<V: Visitor + ?Sized>(
+    visitor: &mut V,
+    persons: &[&[Person]],
+    links: &[Link],
+    categories: &[Category],
+) {
+    for person in persons.iter().flat_map(|persons| persons.iter()) {
+        visitor.visit_person(person);
+    }
+    for link in links {
+        visitor.visit_link(link);
+    }
+    for category in categories {
+        visitor.visit_category(category);
+    }
+}
+
+fn visit_source<V: Visitor + ?Sized>(source: &Source, visitor: &mut V) {
+    let persons = [source.authors(), source.contributors()];
+    visit_all(visitor, &persons, source.links(), source.categories());
+}
+
+fn visit_entry<V: Visitor + ?Sized>(entry: &Entry, visitor: &mut V) {
+    let persons = [entry.authors(), entry.contributors()];
+    visit_all(visitor, &persons, entry.links(), entry.categories());
+    if let Some(source) = entry.source() {
+        visit_source(source, visitor);
+    }
+}
+
+/// Walks an Atom feed, visiting its own persons, links, and categories
+/// and then those of each entry, followed by the entry's source.
+pub fn visit_feed<V: Visitor + ?Sized>(feed: &Feed, visitor: &mut V) {
+    let persons = [feed.authors(), feed.contributors()];
+    visit_all(visitor, &persons, feed.links(), feed.categories());
+    for entry in feed.entries() {
+        visit_entry(entry, visitor);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use atom_syndication::{Category, Link, Person};
+
+    use crate::parse::Feed;
+    use super::Visitor;
+
+    #[derive(Default)]
+    struct Counter {
+        persons: usize,
+        links: Vec<String>,
+        categories: usize,
+    }
+
+    impl Visitor for Counter {
+        fn visit_person(&mut self, _person: &Person) {
+            self.persons += 1;
+        }
+
+        fn visit_link(&mut self, link: &Link) {
+            self.links.push(link.href().to_owned());
+        }
+
+        fn visit_category(&mut self, _category: &Category) {
+            self.categories += 1;
+        }
+    }
+
+    #[test]
+    fn test_visit_feed() {
+        let feed_str = r#"
+<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <id>urn:uuid:b3420f84-6bdf-4f46-a225-f1b9a14703b6</id>
+  <title>TechCrunch</title>
+  <updated>2019-04-01T07:30:00Z</updated>
+  <link href="http://techcrunch.com/"/>
+  <link rel="self" href="http://techcrunch.com/feed/"/>
+  <author><name>TechCrunch Staff</name></author>
+  <category term="tech"/>
+  <entry>
+    <id>urn:uuid:4ae8550b-2987-49fa-9f8c-54c180c418ac</id>
+    <title>Ford hires Elon Musk as CEO</title>
+    <updated>2019-04-01T07:30:00Z</updated>
+    <link href="http://techcrunch.com/2019/04/01/ford-elon-musk/"/>
+    <link rel="replies" href="http://techcrunch.com/2019/04/01/ford-elon-musk/comments/"/>
+    <author><name>Matthew Lynley</name></author>
+    <contributor><name>Sarah Perez</name></contributor>
+    <category term="cars"/>
+    <source>
+      <id>urn:uuid:60a76c80-d399-11d9-b93C-0003939e0af6</id>
+      <title>Ford News</title>
+      <updated>2019-04-01T07:00:00Z</updated>
+      <link href="http://ford.com/news/"/>
+      <category term="ford"/>
+    </source>
+  </entry>
+  <entry>
+    <id>urn:uuid:9b1d4fc3-5a8e-4b0c-8c2e-2b7f1d3e6a10</id>
+    <title>Elon Musk leaves Ford</title>
+    <updated>2019-04-02T07:30:00Z</updated>
+    <link href="http://techcrunch.com/2019/04/02/elon-musk-leaves-ford/"/>
+  </entry>
+</feed>
+"#;
+        let feed = Feed::parse(feed_str.as_bytes()).unwrap();
+        let mut counter = Counter::default();
+        feed.accept(&mut counter);
+
+        assert_eq!(counter.links, [
+            "http://techcrunch.com/",
+            "http://techcrunch.com/feed/",
+            "http://techcrunch.com/2019/04/01/ford-elon-musk/",
+            "http://techcrunch.com/2019/04/01/ford-elon-musk/comments/",
+            "http://ford.com/news/",
+            "http://techcrunch.com/2019/04/02/elon-musk-leaves-ford/",
+        ]);
+        assert_eq!(counter.persons, 3);
+        assert_eq!(counter.categories, 3);
+    }
+}
@@ -0,0 +1,27 @@
+//! The extension traits of this crate, so that their methods can be called on
+//! parsed feeds and atom_syndication types with a single import.
+//!
+//! ```
+//! use atom_syndication as atom;
+//! use feeds::parse::Feed;
+//! use feeds::prelude::*;
+//!
+//! let source = br#"<rss version="2.0"><channel>
+//!   <title>TechCrunch</title>
+//!   <link>http://techcrunch.com</link>
+//!   <description>Startup and technology news</description>
+//!   <item><title>Ford hires Elon Musk as CEO</title><pubDate>01 Apr 2019 07:30:00 GMT</pubDate></item>
+//! </channel></rss>"#;
+//! let mut atom_feed = atom::Feed::from(Feed::parse(source).unwrap());
+//! atom_feed.touch_updated();
+//! assert!(atom_feed.links()[0].rel_is("alternate"));
+//!
+//! let reparsed = Feed::parse(atom_feed.to_string().as_bytes()).unwrap();
+//! assert_eq!(reparsed.title(), "TechCrunch");
+//! assert_eq!(reparsed.entries().count(), 1);
+//! ```
+
+// These share names, so only their methods are imported
+pub use crate::build::{EntryExt as _, FeedExt as _};
+pub use crate::itunes::{EntryExt as _, FeedExt as _};
+pub use crate::parse::{LinkExt, PersonExt, Visitor};
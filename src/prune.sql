@@ -11,7 +11,7 @@ WITH numbered_item_stats AS (
         min(id) AS min_id,
         min(published) AS min_published
     FROM numbered_item_stats
-    WHERE feed_num <= 10
+    WHERE feed_num <= $1
     GROUP BY feed_id
 )
 DELETE FROM item
@@ -0,0 +1,515 @@
+use atom_syndication as atom;
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use diesel::pg::PgConnection;
+use md5::{Digest, Md5};
+use serde_json::{Map, Value, json};
+
+use crate::data;
+use crate::error::Error;
+use crate::models::feed::Feed as DbFeed;
+use crate::models::item::Item as DbItem;
+use crate::build::FeedExt;
+use crate::hash::md5_hex;
+use crate::parse::{LinkExt, find_alternate};
+
+type DataResult<T> = Result<T, Error<diesel::result::Error>>;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Atom,
+    JsonFeed,
+}
+
+impl Format {
+    fn for_media_type(media_type: &str) -> Option<Format> {
+        match media_type {
+            "application/atom+xml" | "application/xml" | "text/xml" |
+            "application/*" | "*/*" => Some(Format::Atom),
+            "application/feed+json" | "application/json" => Some(Format::JsonFeed),
+            _ => None,
+        }
+    }
+
+    /// Picks the format with the highest quality in an Accept header,
+    /// defaulting to Atom when the header is missing or matches nothing.
+    pub fn negotiate(accept: Option<&str>) -> Format {
+        let mut best = None;
+        for range in accept.unwrap_or("").split(',') {
+            let mut params = range.split(';').map(str::trim);
+            let media_type = params.next().unwrap_or("").to_ascii_lowercase();
+            let quality = params
+                .filter_map(|param| param.strip_prefix("q="))
+                .filter_map(|q| q.parse::<f32>().ok())
+                .next()
+                .unwrap_or(1.0);
+
+            let Some(format) = Format::for_media_type(&media_type) else {
+                continue;
+            };
+            let is_better = match best {
+                Some((_, best_quality)) => quality > best_quality,
+                None => quality > 0.0,
+            };
+            if is_better {
+                best = Some((format, quality));
+            }
+        }
+        best.map_or(Format::Atom, |(format, _)| format)
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Format::Atom => "application/atom+xml",
+            Format::JsonFeed => "application/feed+json",
+        }
+    }
+}
+
+/// Options for feeds republished by the server.
+#[derive(Clone, Debug, Default)]
+pub struct PublishOptions {
+    /// The public url of the server, which self links are built from.
+    pub base_url: Option<String>,
+    /// The WebSub hub that subscribers are pointed to for updates.
+    pub hub_url: Option<String>,
+}
+
+impl PublishOptions {
+    /// The url a feed at the given path is served at, or None if there's no
+    /// configured base url. The Host header isn't trusted to build one,
+    /// since any client could set it and it'd end up in cached responses.
+    pub fn self_url(&self, path: &str) -> Option<String> {
+        let base_url = self.base_url.as_deref()?.trim_end_matches('/');
+        Some(format!("{}{}", base_url, path))
+    }
+}
+
+/// Adds the links WebSub subscribers discover a feed's hub with:
+/// a self link to the url it's served at and a hub link, if there's a hub.
+pub fn add_websub_links(atom_feed: &mut atom::Feed, self_url: Option<&str>, options: &PublishOptions) {
+    if let Some(self_url) = self_url {
+        atom_feed.set_self_link(self_url);
+    }
+    if let Some(hub_url) = &options.hub_url {
+        atom_feed.links.retain(|link| !link.rel_is("hub"));
+        atom_feed.links.push(atom::Link {
+            href: hub_url.clone(),
+            rel: "hub".to_owned(),
+            ..Default::default()
+        });
+    }
+}
+
+fn utc_date(date: NaiveDateTime) -> atom::FixedDateTime {
+    Utc.from_utc_datetime(&date).into()
+}
+
+/// The updated date of a feed without entries, which is fixed rather than
+/// the current time so its ETag and Last-Modified stay the same.
+fn empty_feed_updated() -> atom::FixedDateTime {
+    Utc.timestamp_opt(0, 0).unwrap().into()
+}
+
+fn item_id(item: &DbItem) -> String {
+    // Database ensures that both cannot be null
+    item.guid.as_ref().or(item.url.as_ref()).cloned().unwrap_or_default()
+}
+
+fn atom_entry(item: &DbItem) -> atom::Entry {
+    let links = item.url.iter()
+        .map(|url| atom::Link { href: url.clone(), ..Default::default() })
+        .collect();
+    let authors = item.author.iter()
+        .map(|author| atom::Person { name: author.clone(), ..Default::default() })
+        .collect();
+
+    atom::Entry {
+        title: item.title.clone().into(),
+        id: item_id(item),
+        updated: utc_date(item.updated.unwrap_or(item.published)),
+        published: Some(utc_date(item.published)),
+        authors,
+        links,
+        content: Some(atom::Content {
+            value: Some(item.content.clone()),
+            content_type: Some("html".to_owned()),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+pub fn atom_feed(feed: &DbFeed, items: &[DbItem]) -> atom::Feed {
+    let links = feed.site_url.iter()
+        .map(|url| atom::Link { href: url.clone(), ..Default::default() })
+        .collect();
+
+    let mut atom_feed = atom::Feed {
+        title: feed.title.clone().into(),
+        id: feed.url.clone(),
+        updated: empty_feed_updated(),
+        links,
+        entries: items.iter().map(atom_entry).collect(),
+        ..Default::default()
+    };
+    atom_feed.touch_updated();
+    atom_feed
+}
+
+/// Describes the feed an item came from, for aggregated feeds.
+fn atom_source(feed: &DbFeed) -> atom::Source {
+    atom::Source {
+        title: feed.title.clone().into(),
+        id: feed.url.clone(),
+        links: feed.site_url.iter()
+            .map(|url| atom::Link { href: url.clone(), ..Default::default() })
+            .collect(),
+        subtitle: feed.subtitle.clone().map(Into::into),
+        ..Default::default()
+    }
+}
+
+/// Builds a single feed of unread items from every subscription,
+/// with each entry's source set to the feed it came from.
+pub fn unread_atom_feed(items: &[(DbItem, DbFeed)]) -> atom::Feed {
+    let entries = items.iter()
+        .map(|(item, feed)| atom::Entry {
+            source: Some(atom_source(feed)),
+            ..atom_entry(item)
+        })
+        .collect();
+
+    let mut atom_feed = atom::Feed {
+        title: "Unread items".into(),
+        id: "urn:feeds:unread".to_owned(),
+        updated: empty_feed_updated(),
+        entries,
+        ..Default::default()
+    };
+    atom_feed.touch_updated();
+    atom_feed
+}
+
+fn insert_opt<T: Into<Value>>(map: &mut Map<String, Value>, key: &str, value: Option<T>) {
+    if let Some(value) = value {
+        map.insert(key.to_owned(), value.into());
+    }
+}
+
+fn json_feed_item(entry: &atom::Entry) -> Value {
+    let mut item = Map::new();
+    item.insert("id".to_owned(), entry.id().into());
+    insert_opt(&mut item, "url", find_alternate(entry.links()).map(|link| link.href()));
+    item.insert("title".to_owned(), entry.title().as_str().into());
+
+    // JSON Feed only has html and plain text bodies, so anything that isn't
+    // Atom text content is treated as html
+    let content = entry.content()
+        .and_then(|content| content.value().map(|value| (content.content_type(), value)));
+    match content {
+        Some((Some("text"), value)) => {
+            item.insert("content_text".to_owned(), value.into());
+        }
+        Some((_, value)) => {
+            item.insert("content_html".to_owned(), value.into());
+        }
+        None => {
+            // Items are required to have some content
+            item.insert("content_text".to_owned(), "".into());
+        }
+    }
+    insert_opt(&mut item, "summary", entry.summary().map(|summary| summary.as_str()));
+
+    insert_opt(&mut item, "date_published", entry.published().map(|date| date.to_rfc3339()));
+    item.insert("date_modified".to_owned(), entry.updated().to_rfc3339().into());
+
+    let authors: Vec<_> = entry.authors().iter()
+        .map(|author| json!({ "name": author.name() }))
+        .collect();
+    if !authors.is_empty() {
+        item.insert("authors".to_owned(), authors.into());
+    }
+
+    item.into()
+}
+
+/// Serializes an Atom feed as a JSON Feed 1.1 document.
+pub fn to_json_feed(feed: &atom::Feed) -> String {
+    let mut json_feed = Map::new();
+    json_feed.insert("version".to_owned(), "https://jsonfeed.org/version/1.1".into());
+    json_feed.insert("title".to_owned(), feed.title().as_str().into());
+    insert_opt(&mut json_feed, "home_page_url", find_alternate(feed.links()).map(|link| link.href()));
+    insert_opt(&mut json_feed, "feed_url",
+        feed.links().iter().find(|link| link.is_self()).map(|link| link.href()));
+    insert_opt(&mut json_feed, "description", feed.subtitle().map(|subtitle| subtitle.as_str()));
+    insert_opt(&mut json_feed, "icon", feed.logo());
+    insert_opt(&mut json_feed, "favicon", feed.icon());
+
+    let hubs: Vec<_> = feed.links().iter()
+        .filter(|link| link.rel_is("hub"))
+        .map(|link| json!({ "type": "WebSub", "url": link.href() }))
+        .collect();
+    if !hubs.is_empty() {
+        json_feed.insert("hubs".to_owned(), hubs.into());
+    }
+
+    let items: Vec<_> = feed.entries().iter().map(json_feed_item).collect();
+    json_feed.insert("items".to_owned(), items.into());
+
+    Value::Object(json_feed).to_string()
+}
+
+/// A rendered feed, with what's needed to answer conditional requests for it.
+pub struct RenderedFeed {
+    pub body: String,
+    pub updated: DateTime<Utc>,
+}
+
+impl RenderedFeed {
+    /// A strong ETag for the body, including its surrounding quotes.
+    pub fn etag(&self) -> String {
+        format!("\"{}\"", md5_hex(Md5::new_with_prefix(&self.body)))
+    }
+
+    /// The updated date formatted as an HTTP date.
+    pub fn last_modified(&self) -> String {
+        self.updated.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+    }
+}
+
+fn render(atom_feed: &atom::Feed, format: Format) -> RenderedFeed {
+    let body = match format {
+        Format::Atom => atom_feed.to_string(),
+        Format::JsonFeed => to_json_feed(atom_feed),
+    };
+    let updated = atom_feed.updated.with_timezone(&Utc);
+    RenderedFeed { body, updated }
+}
+
+/// Loads a feed and its latest items and renders them in the given format,
+/// served at the given url, or returns None if there's no feed with the id.
+pub fn render_feed(
+    id: i32,
+    format: Format,
+    self_url: Option<&str>,
+    options: &PublishOptions,
+    conn: &mut PgConnection,
+) -> DataResult<Option<RenderedFeed>> {
+    let feed = data::load_feed(id, conn)
+        .map_err(fill_err!("Error loading feed"))?;
+    let Some(feed) = feed else {
+        return Ok(None);
+    };
+    let items = data::load_feed_items(&feed, conn)
+        .map_err(fill_err!("Error loading feed items"))?;
+
+    let mut atom_feed = atom_feed(&feed, &items);
+    add_websub_links(&mut atom_feed, self_url, options);
+    Ok(Some(render(&atom_feed, format)))
+}
+
+/// Renders the latest unread items across every feed in the given format,
+/// served at the given url.
+pub fn render_unread(
+    limit: i64,
+    format: Format,
+    self_url: Option<&str>,
+    options: &PublishOptions,
+    conn: &mut PgConnection,
+) -> DataResult<RenderedFeed> {
+    let items = data::load_unread_items(limit, conn)
+        .map_err(fill_err!("Error loading unread items"))?;
+    let mut atom_feed = unread_atom_feed(&items);
+    add_websub_links(&mut atom_feed, self_url, options);
+    Ok(render(&atom_feed, format))
+}
+
+#[cfg(test)]
+mod tests {
+    use atom_syndication as atom;
+    use serde_json::Value;
+
+    use crate::models::feed::Feed as DbFeed;
+    use crate::testing::{item, self};
+    use super::{
+        Format,
+        PublishOptions,
+        add_websub_links,
+        atom_feed,
+        render,
+        to_json_feed,
+        unread_atom_feed,
+    };
+
+    fn feed(id: i32, title: &str, url: &str, site_url: &str) -> DbFeed {
+        DbFeed {
+            title: title.to_owned(),
+            site_url: Some(site_url.to_owned()),
+            ..testing::feed(id, url)
+        }
+    }
+
+    static ATOM_STR: &str = r#"
+<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <id>urn:uuid:b3420f84-6bdf-4f46-a225-f1b9a14703b6</id>
+  <title>TechCrunch</title>
+  <link href="http://techcrunch.com"/>
+  <updated>2019-04-01T07:30:00Z</updated>
+  <entry>
+    <id>urn:uuid:4ae8550b-2987-49fa-9f8c-54c180c418ac</id>
+    <title>Ford hires Elon Musk as CEO</title>
+    <link href="http://techcrunch.com/2019/04/01/ford-elon-musk/"/>
+    <updated>2019-04-01T07:30:00Z</updated>
+    <content type="html">&lt;p&gt;In an unprecedented move, Ford hires Elon Musk.&lt;/p&gt;</content>
+  </entry>
+  <entry>
+    <id>urn:uuid:9b1d4fc3-5a8e-4b0c-8c2e-2b7f1d3e6a10</id>
+    <title>Elon Musk leaves Ford</title>
+    <updated>2019-04-02T07:30:00Z</updated>
+    <content type="text">Elon Musk has left Ford.</content>
+  </entry>
+</feed>
+"#;
+
+    #[test]
+    fn test_json_feed() {
+        let feed: atom::Feed = ATOM_STR.parse().unwrap();
+        let json_feed: Value = serde_json::from_str(&to_json_feed(&feed)).unwrap();
+
+        assert_eq!(json_feed["version"], "https://jsonfeed.org/version/1.1");
+        assert_eq!(json_feed["title"], "TechCrunch");
+        assert_eq!(json_feed["home_page_url"], "http://techcrunch.com");
+
+        let items = json_feed["items"].as_array().unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0]["id"], "urn:uuid:4ae8550b-2987-49fa-9f8c-54c180c418ac");
+        assert_eq!(items[0]["url"], "http://techcrunch.com/2019/04/01/ford-elon-musk/");
+        assert_eq!(items[0]["content_html"], "<p>In an unprecedented move, Ford hires Elon Musk.</p>");
+        assert_eq!(items[0]["date_modified"], "2019-04-01T07:30:00+00:00");
+        assert_eq!(items[1]["content_text"], "Elon Musk has left Ford.");
+        assert!(items[1].get("url").is_none());
+    }
+
+    #[test]
+    fn test_unread_atom_feed() {
+        let techcrunch = feed(1, "TechCrunch",
+            "http://techcrunch.com/feed/", "http://techcrunch.com");
+        let verge = feed(2, "The Verge",
+            "http://www.theverge.com/rss/index.xml", "http://www.theverge.com");
+        let items = vec![
+            (item(3, &verge, "Tesla stock falls", 3), verge),
+            (item(2, &techcrunch, "Elon Musk leaves Ford", 2), techcrunch),
+        ];
+        let unread_feed = unread_atom_feed(&items);
+        assert_eq!(unread_feed.updated().to_rfc3339(), "2019-04-03T07:30:00+00:00");
+        // Without entries the date doesn't change between requests
+        assert_eq!(unread_atom_feed(&[]).updated().to_rfc3339(), "1970-01-01T00:00:00+00:00");
+
+        let entries = unread_feed.entries();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].title().as_str(), "Tesla stock falls");
+        let source = entries[0].source().unwrap();
+        assert_eq!(source.title().as_str(), "The Verge");
+        assert_eq!(source.id(), "http://www.theverge.com/rss/index.xml");
+        assert_eq!(source.links()[0].href(), "http://www.theverge.com");
+
+        assert_eq!(entries[1].title().as_str(), "Elon Musk leaves Ford");
+        assert_eq!(entries[1].source().unwrap().title().as_str(), "TechCrunch");
+
+        // Sources survive being written out
+        let written: atom::Feed = unread_feed.to_string().parse().unwrap();
+        let source = written.entries()[1].source().unwrap();
+        assert_eq!(source.id(), "http://techcrunch.com/feed/");
+    }
+
+    #[test]
+    fn test_websub_links() {
+        let options = PublishOptions {
+            base_url: Some("https://feeds.example.com/".to_owned()),
+            hub_url: Some("https://hub.example.com/".to_owned()),
+        };
+        let self_url = options.self_url("/feeds/1");
+        assert_eq!(self_url.as_deref(), Some("https://feeds.example.com/feeds/1"));
+
+        let techcrunch = feed(1, "TechCrunch",
+            "http://techcrunch.com/feed/", "http://techcrunch.com");
+        let mut techcrunch_feed = atom_feed(&techcrunch, &[item(1, &techcrunch, "Ford hires Elon Musk", 1)]);
+        add_websub_links(&mut techcrunch_feed, self_url.as_deref(), &options);
+
+        let served: atom::Feed = render(&techcrunch_feed, Format::Atom).body.parse().unwrap();
+        let links: Vec<_> = served.links().iter()
+            .map(|link| (link.rel(), link.href()))
+            .collect();
+        assert_eq!(links, vec![
+            ("alternate", "http://techcrunch.com"),
+            ("self", "https://feeds.example.com/feeds/1"),
+            ("hub", "https://hub.example.com/"),
+        ]);
+
+        let json_feed: Value = serde_json::from_str(&render(&techcrunch_feed, Format::JsonFeed).body).unwrap();
+        assert_eq!(json_feed["feed_url"], "https://feeds.example.com/feeds/1");
+        assert_eq!(json_feed["hubs"][0]["type"], "WebSub");
+        assert_eq!(json_feed["hubs"][0]["url"], "https://hub.example.com/");
+    }
+
+    #[test]
+    fn test_self_url() {
+        let options = PublishOptions::default();
+        assert_eq!(options.self_url("/feeds/unread"), None);
+        let configured = PublishOptions {
+            base_url: Some("http://localhost:3000".to_owned()),
+            ..PublishOptions::default()
+        };
+        assert_eq!(configured.self_url("/feeds/unread").as_deref(),
+            Some("http://localhost:3000/feeds/unread"));
+
+        // Without a hub, only the self link is added
+        let mut unread_feed = unread_atom_feed(&[]);
+        add_websub_links(&mut unread_feed, Some("http://localhost:3000/feeds/unread"), &options);
+        assert_eq!(unread_feed.links().len(), 1);
+        assert_eq!(unread_feed.links()[0].rel(), "self");
+    }
+
+    #[test]
+    fn test_negotiate() {
+        assert_eq!(Format::negotiate(None), Format::Atom);
+        assert_eq!(Format::negotiate(Some("application/atom+xml")), Format::Atom);
+        assert_eq!(Format::negotiate(Some("application/feed+json")), Format::JsonFeed);
+        assert_eq!(Format::negotiate(Some("text/html")), Format::Atom);
+
+        let accept = "application/atom+xml;q=0.5, application/feed+json";
+        assert_eq!(Format::negotiate(Some(accept)), Format::JsonFeed);
+        let accept = "application/feed+json;q=0, */*";
+        assert_eq!(Format::negotiate(Some(accept)), Format::Atom);
+    }
+
+    #[test]
+    fn test_atom_attribute_order() {
+        let feed = atom::Feed {
+            title: "TechCrunch".into(),
+            links: vec![atom::Link {
+                href: "http://techcrunch.com/feed/".to_owned(),
+                rel: "self".to_owned(),
+                mime_type: Some("application/atom+xml".to_owned()),
+                ..Default::default()
+            }],
+            categories: vec![atom::Category {
+                term: "tech".to_owned(),
+                scheme: Some("http://techcrunch.com/tags".to_owned()),
+                label: Some("Tech".to_owned()),
+            }],
+            ..Default::default()
+        };
+
+        // Attributes are written in a fixed order, so the output can be compared
+        let output = feed.to_string();
+        assert!(output.contains(
+            r#"<link href="http://techcrunch.com/feed/" rel="self" type="application/atom+xml"/>"#
+        ));
+        assert!(output.contains(
+            r#"<category term="tech" scheme="http://techcrunch.com/tags" label="Tech"/>"#
+        ));
+        assert_eq!(output, feed.to_string());
+    }
+}
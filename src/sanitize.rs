@@ -0,0 +1,256 @@
+use crate::parse::decode_entities;
+
+/// Elements that are kept, without any attributes beyond ALLOWED_ATTRS.
+static ALLOWED_TAGS: &[&str] = &[
+    "a", "abbr", "audio", "b", "blockquote", "br", "caption", "cite", "code",
+    "dd", "del", "div", "dl", "dt", "em", "figcaption", "figure",
+    "h1", "h2", "h3", "h4", "h5", "h6", "hr", "i", "img", "ins", "kbd", "li",
+    "mark", "ol", "p", "picture", "pre", "q", "s", "small", "source", "span",
+    "strong", "sub", "sup", "table", "tbody", "td", "tfoot", "th", "thead",
+    "time", "tr", "u", "ul", "video",
+];
+
+/// Elements that are removed along with everything inside them.
+static DROPPED_TAGS: &[&str] = &[
+    "embed", "iframe", "math", "noscript", "object", "script", "select",
+    "style", "svg", "template", "textarea", "title",
+];
+
+static ALLOWED_ATTRS: &[&str] = &[
+    "alt", "cite", "colspan", "controls", "datetime", "dir", "height", "href",
+    "lang", "poster", "reversed", "rowspan", "src", "start", "title", "type",
+    "width",
+];
+
+static URL_ATTRS: &[&str] = &["cite", "href", "poster", "src"];
+
+static ALLOWED_SCHEMES: &[&str] = &["http", "https", "mailto"];
+
+struct Tag<'a> {
+    name: String,
+    closing: bool,
+    attrs: Vec<(String, Option<&'a str>)>,
+}
+
+/// Checks that a decoded url is relative or uses a scheme that can't run
+/// scripts. Browsers ignore control characters and whitespace in urls,
+/// so they're removed before finding the scheme, and anything that still
+/// looks like an escaped scheme is rejected.
+fn is_safe_url(url: &str) -> bool {
+    let url: String = url.chars()
+        .filter(|c| !c.is_ascii_control() && !c.is_whitespace())
+        .collect();
+    let end = url.find([':', '/', '?', '#']).unwrap_or(url.len());
+    let scheme = &url[..end];
+    if scheme.contains('&') {
+        return false;
+    }
+    if !url[end..].starts_with(':') {
+        return true;
+    }
+    ALLOWED_SCHEMES.iter().any(|allowed| allowed.eq_ignore_ascii_case(scheme))
+}
+
+fn is_attr_name_end(c: char) -> bool {
+    c.is_whitespace() || matches!(c, '=' | '>' | '/')
+}
+
+/// Parses the tag at the start of the html, returning it and the rest
+/// of the html after it, or None if it isn't a complete tag.
+fn parse_tag(html: &str) -> Option<(Tag<'_>, &str)> {
+    let rest = html.strip_prefix('<')?;
+    let (closing, rest) = match rest.strip_prefix('/') {
+        Some(rest) => (true, rest),
+        None => (false, rest),
+    };
+    let name_len = rest.find(|c: char| !c.is_ascii_alphanumeric()).unwrap_or(rest.len());
+    if name_len == 0 {
+        return None;
+    }
+    let name = rest[..name_len].to_ascii_lowercase();
+    let mut rest = &rest[name_len..];
+
+    let mut attrs = Vec::new();
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == '/');
+        if let Some(rest) = rest.strip_prefix('>') {
+            return Some((Tag { name, closing, attrs }, rest));
+        }
+        if rest.is_empty() {
+            return None;
+        }
+
+        let attr_len = rest.find(is_attr_name_end).unwrap_or(rest.len()).max(1);
+        let attr = rest[..attr_len].to_ascii_lowercase();
+        rest = rest[attr_len..].trim_start();
+
+        let value = match rest.strip_prefix('=') {
+            Some(value_start) => {
+                let value_start = value_start.trim_start();
+                let (value, after) = match value_start.chars().next() {
+                    Some(quote @ ('"' | '\'')) => {
+                        let value_start = &value_start[1..];
+                        let end = value_start.find(quote)?;
+                        (&value_start[..end], &value_start[end + 1..])
+                    }
+                    _ => {
+                        let end = value_start.find(|c: char| c.is_whitespace() || c == '>')
+                            .unwrap_or(value_start.len());
+                        value_start.split_at(end)
+                    }
+                };
+                rest = after;
+                Some(value)
+            }
+            None => None,
+        };
+        attrs.push((attr, value));
+    }
+}
+
+/// Finds the end of an element's closing tag, ignoring case.
+fn skip_element<'a>(html: &'a str, name: &str) -> &'a str {
+    let closing = format!("</{}", name);
+    let lowercase = html.to_ascii_lowercase();
+    let end = lowercase.find(&closing)
+        .and_then(|start| html[start..].find('>').map(|end| start + end + 1));
+    end.map_or("", |end| &html[end..])
+}
+
+fn write_tag(tag: &Tag, output: &mut String) {
+    output.push('<');
+    if tag.closing {
+        output.push('/');
+        output.push_str(&tag.name);
+        output.push('>');
+        return;
+    }
+
+    output.push_str(&tag.name);
+    for (attr, value) in &tag.attrs {
+        if !ALLOWED_ATTRS.contains(&attr.as_str()) {
+            continue;
+        }
+        match value {
+            Some(value) => {
+                // Values are decoded so they're checked the way browsers
+                // read them, then written with everything escaped again
+                let value = decode_entities(value);
+                if URL_ATTRS.contains(&attr.as_str()) && !is_safe_url(&value) {
+                    continue;
+                }
+                output.push(' ');
+                output.push_str(attr);
+                output.push_str("=\"");
+                for c in value.chars() {
+                    match c {
+                        '&' => output.push_str("&amp;"),
+                        '"' => output.push_str("&quot;"),
+                        '<' => output.push_str("&lt;"),
+                        '>' => output.push_str("&gt;"),
+                        c => output.push(c),
+                    }
+                }
+                output.push('"');
+            }
+            None => {
+                output.push(' ');
+                output.push_str(attr);
+            }
+        }
+    }
+    output.push('>');
+}
+
+/// Removes anything from html that could run scripts or change the page
+/// around it, keeping common formatting, links, and media.
+/// Unknown elements are removed but their text is kept, while scripts,
+/// styles, and embedded content are removed entirely.
+pub fn sanitize_html(html: &str) -> String {
+    let mut output = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+        if rest.starts_with("<!") || rest.starts_with("<?") {
+            rest = rest.find('>').map_or("", |end| &rest[end + 1..]);
+            continue;
+        }
+
+        match parse_tag(rest) {
+            Some((tag, after)) => {
+                rest = after;
+                if DROPPED_TAGS.contains(&tag.name.as_str()) {
+                    if !tag.closing {
+                        rest = skip_element(rest, &tag.name);
+                    }
+                } else if ALLOWED_TAGS.contains(&tag.name.as_str()) {
+                    write_tag(&tag, &mut output);
+                }
+            }
+            None => {
+                output.push_str("&lt;");
+                rest = &rest[1..];
+            }
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_safe_url, sanitize_html};
+
+    #[test]
+    fn test_sanitize_scripts() {
+        let html = r#"<p>Ford hires <b>Elon Musk</b></p><script>alert("hi")</script><SCRIPT src="x.js"></Script >"#;
+        assert_eq!(sanitize_html(html), "<p>Ford hires <b>Elon Musk</b></p>");
+        assert_eq!(sanitize_html("<style>p { display: none }</style>Hi"), "Hi");
+        assert_eq!(sanitize_html("<script>unclosed"), "");
+    }
+
+    #[test]
+    fn test_sanitize_attrs() {
+        let html = r#"<a href="http://techcrunch.com" onclick="steal()" class=x>TechCrunch</a>"#;
+        assert_eq!(sanitize_html(html), r#"<a href="http://techcrunch.com">TechCrunch</a>"#);
+
+        let html = r#"<img src='/ford.jpg' alt="Ford &amp; Tesla" onerror=steal()>"#;
+        assert_eq!(sanitize_html(html), r#"<img src="/ford.jpg" alt="Ford &amp; Tesla">"#);
+
+        let html = r#"<a href=" JavaScript:steal()">Ford</a><a href="&#106;avascript:steal()">Musk</a>"#;
+        assert_eq!(sanitize_html(html), "<a>Ford</a><a>Musk</a>");
+
+        let html = r#"<a href="javascript&colon;steal()">Ford</a><a href="javascript&#58;steal()">Musk</a>"#;
+        assert_eq!(sanitize_html(html), "<a>Ford</a><a>Musk</a>");
+        let html = "<a href=\"java&#9;script:steal()\">Ford</a><a href=\"java\nscript&#x3A;steal()\">Musk</a>";
+        assert_eq!(sanitize_html(html), "<a>Ford</a><a>Musk</a>");
+        let html = r#"<a href="javascript&unknown;steal()">Ford</a>"#;
+        assert_eq!(sanitize_html(html), "<a>Ford</a>");
+
+        let html = r#"<a href="/search?q=ford&amp;page=2">Ford</a>"#;
+        assert_eq!(sanitize_html(html), r#"<a href="/search?q=ford&amp;page=2">Ford</a>"#);
+    }
+
+    #[test]
+    fn test_sanitize_unknown_tags() {
+        let html = "<form><input>Ford</form><!-- comment --> 1 < 2 <video controls></video>";
+        assert_eq!(sanitize_html(html), "Ford 1 &lt; 2 <video controls></video>");
+    }
+
+    #[test]
+    fn test_is_safe_url() {
+        assert!(is_safe_url("https://techcrunch.com"));
+        assert!(is_safe_url("mailto:tips@techcrunch.com"));
+        assert!(is_safe_url("/posts/1?a=b:c"));
+        assert!(!is_safe_url("javascript:alert(1)"));
+        assert!(!is_safe_url("data:text/html,hi"));
+        assert!(!is_safe_url("\u{1}java\tscript:alert(1)"));
+        assert!(!is_safe_url("javascript&colon;alert(1)"));
+    }
+}
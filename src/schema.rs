@@ -1,5 +1,31 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    archived_item (id) {
+        id -> Int4,
+        url -> Nullable<Varchar>,
+        title -> Varchar,
+        content -> Text,
+        published -> Timestamp,
+        feed_id -> Int4,
+        is_read -> Bool,
+        is_saved -> Bool,
+        author -> Nullable<Varchar>,
+        fetched -> Timestamp,
+        guid -> Nullable<Varchar>,
+        summary -> Nullable<Text>,
+        updated -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    archived_item_category (item_id, term) {
+        item_id -> Int4,
+        term -> Varchar,
+        scheme -> Nullable<Varchar>,
+    }
+}
+
 diesel::table! {
     feed (id) {
         id -> Int4,
@@ -7,6 +33,26 @@ diesel::table! {
         title -> Varchar,
         group_id -> Nullable<Int4>,
         site_url -> Nullable<Varchar>,
+        is_disabled -> Bool,
+        subtitle -> Nullable<Varchar>,
+        has_custom_title -> Bool,
+        auth_username -> Nullable<Varchar>,
+        auth_password -> Nullable<Varchar>,
+        auth_token -> Nullable<Varchar>,
+        next_fetch -> Nullable<Timestamp>,
+        read_before -> Nullable<Timestamp>,
+        last_warnings -> Nullable<Text>,
+        mark_read_on_fetch -> Bool,
+        fingerprint -> Nullable<Int8>,
+        backfill_url -> Nullable<Varchar>,
+    }
+}
+
+diesel::table! {
+    feed_body (feed_id) {
+        feed_id -> Int4,
+        body -> Bytea,
+        fetched -> Timestamp,
     }
 }
 
@@ -17,6 +63,18 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    fetch_log (id) {
+        id -> Int4,
+        feed_id -> Int4,
+        fetched -> Timestamp,
+        status -> Varchar,
+        http_code -> Nullable<Int4>,
+        item_count -> Int4,
+        error -> Nullable<Text>,
+    }
+}
+
 diesel::table! {
     item (id) {
         id -> Int4,
@@ -30,14 +88,34 @@ diesel::table! {
         author -> Nullable<Varchar>,
         fetched -> Timestamp,
         guid -> Nullable<Varchar>,
+        summary -> Nullable<Text>,
+        updated -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    item_category (item_id, term) {
+        item_id -> Int4,
+        term -> Varchar,
+        scheme -> Nullable<Varchar>,
     }
 }
 
+diesel::joinable!(archived_item -> feed (feed_id));
+diesel::joinable!(archived_item_category -> archived_item (item_id));
 diesel::joinable!(feed -> feed_group (group_id));
+diesel::joinable!(feed_body -> feed (feed_id));
+diesel::joinable!(fetch_log -> feed (feed_id));
 diesel::joinable!(item -> feed (feed_id));
+diesel::joinable!(item_category -> item (item_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    archived_item,
+    archived_item_category,
     feed,
+    feed_body,
     feed_group,
+    fetch_log,
     item,
+    item_category,
 );
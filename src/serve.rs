@@ -1,7 +1,9 @@
 use std::collections::HashMap;
 
+use chrono::DateTime;
 use futures::future;
-use warp::{Filter, self};
+use log::debug;
+use warp::{Filter, Reply, self};
 use warp::http::StatusCode;
 
 use fever_api::{
@@ -10,13 +12,31 @@ use fever_api::{
 };
 
 use crate::config::{PgConnectionPool, PooledPgConnection};
+use crate::data;
 use crate::error::Error;
-use crate::fetch;
-use crate::handling;
+use crate::fetch::{Fetcher, IngestOptions, self};
+use crate::handling::{ResponseOptions, self};
+use crate::publish::{Format, PublishOptions, RenderedFeed, self};
 
 impl warp::reject::Reject for Error<diesel::result::Error> { }
 impl warp::reject::Reject for Error<diesel::r2d2::PoolError> { }
 
+/// The request headers that decide how a feed is served.
+struct FeedHeaders {
+    accept: Option<String>,
+    if_none_match: Option<String>,
+    if_modified_since: Option<String>,
+}
+
+fn feed_headers() -> impl Filter<Extract=(FeedHeaders,), Error=warp::Rejection> + Clone {
+    warp::header::optional::<String>("accept")
+        .and(warp::header::optional::<String>("if-none-match"))
+        .and(warp::header::optional::<String>("if-modified-since"))
+        .map(|accept, if_none_match, if_modified_since| {
+            FeedHeaders { accept, if_none_match, if_modified_since }
+        })
+}
+
 fn connect_db(pool: PgConnectionPool)
 -> impl Filter<Extract=(PooledPgConnection,), Error=warp::Rejection> + Clone {
     warp::any().and_then(move || {
@@ -42,8 +62,8 @@ async fn parse_request(
         ApiRequest::parse(query_pairs, &body_params)
     };
 
-    println!("query: {:?}\nparams: {:?}\nparsed: {:?}",
-        query_pairs, body_params, request);
+    // Only the request type is logged, so the api key never is
+    debug!("Fever request: {:?}", request.as_ref().map(|request| &request.req_type));
 
     request.ok_or_else(warp::reject::not_found)
 }
@@ -64,9 +84,11 @@ async fn accept_refresh(
 }
 
 async fn handle_refresh(
+    fetcher: Fetcher,
+    options: IngestOptions,
     mut conn: PooledPgConnection,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    fetch::fetch_items(&mut conn).await
+    fetch::fetch_items(&fetcher, &options, &mut conn).await
         .map(|_| warp::reply())
         .map_err(|err| warp::reject::custom(err))
 }
@@ -74,9 +96,10 @@ async fn handle_refresh(
 async fn handle_request(
     request: ApiRequest,
     key: Option<ApiKey>,
+    options: ResponseOptions,
     mut conn: PooledPgConnection,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    let response = handling::handle_api_request(&request, key.as_ref(), &mut conn)
+    let response = handling::handle_api_request(&request, key.as_ref(), options, &mut conn)
         .map_err(|err| warp::reject::custom(err))?;
     let status = if response.auth {
         StatusCode::OK
@@ -86,19 +109,200 @@ async fn handle_request(
     Ok(warp::reply::with_status(warp::reply::json(&response), status))
 }
 
-pub async fn serve(
-    port: u16,
-    creds: Option<(String, String)>,
+/// Checks the api_key query parameter of requests outside the Fever API.
+fn is_authorized(params: &HashMap<String, String>, key: Option<&ApiKey>) -> bool {
+    // Fever clients authenticate with their key in the body, but feed readers
+    // can only GET a url, so the key is accepted as a query parameter here
+    let api_key = params.get("api_key").and_then(|s| s.parse::<ApiKey>().ok());
+    key.is_none() || api_key.as_ref() == key
+}
+
+/// Checks whether a client's cached copy of a feed is still current,
+/// giving If-None-Match precedence over If-Modified-Since.
+fn is_not_modified(
+    feed: &RenderedFeed,
+    if_none_match: Option<&str>,
+    if_modified_since: Option<&str>,
+) -> bool {
+    if let Some(etags) = if_none_match {
+        let etag = feed.etag();
+        return etags.split(',')
+            .map(|tag| tag.trim().trim_start_matches("W/"))
+            .any(|tag| tag == "*" || tag == etag);
+    }
+
+    match if_modified_since.and_then(|date| DateTime::parse_from_rfc2822(date).ok()) {
+        // HTTP dates only have second precision
+        Some(since) => feed.updated.timestamp() <= since.timestamp(),
+        None => false,
+    }
+}
+
+fn feed_reply(
+    feed: RenderedFeed,
+    format: Format,
+    if_none_match: Option<&str>,
+    if_modified_since: Option<&str>,
+) -> warp::reply::Response {
+    let etag = feed.etag();
+    let last_modified = feed.last_modified();
+    let reply = if is_not_modified(&feed, if_none_match, if_modified_since) {
+        StatusCode::NOT_MODIFIED.into_response()
+    } else {
+        warp::reply::with_header(feed.body, "content-type", format.content_type())
+            .into_response()
+    };
+    let reply = warp::reply::with_header(reply, "etag", etag);
+    let reply = warp::reply::with_header(reply, "last-modified", last_modified);
+    // The format depends on the Accept header, so caches must keep them apart
+    warp::reply::with_header(reply, "vary", "accept").into_response()
+}
+
+async fn handle_feed_output(
+    id: i32,
+    params: HashMap<String, String>,
+    headers: FeedHeaders,
+    key: Option<ApiKey>,
+    options: &PublishOptions,
+    mut conn: PooledPgConnection,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    if !is_authorized(&params, key.as_ref()) {
+        return Ok(warp::reply::with_status(
+            warp::reply::with_header(String::new(), "content-type", "text/plain"),
+            StatusCode::UNAUTHORIZED,
+        ).into_response());
+    }
+
+    let format = Format::negotiate(headers.accept.as_deref());
+    let self_url = options.self_url(&format!("/feeds/{}", id));
+    let feed = publish::render_feed(id, format, self_url.as_deref(), options, &mut conn)
+        .map_err(warp::reject::custom)?
+        .ok_or_else(warp::reject::not_found)?;
+    Ok(feed_reply(
+        feed, format, headers.if_none_match.as_deref(), headers.if_modified_since.as_deref(),
+    ))
+}
+
+/// The most unread items served in the combined feed.
+const MAX_UNREAD_ITEMS: i64 = 500;
+const DEFAULT_UNREAD_ITEMS: i64 = 50;
+
+/// Reads the limit query parameter of the unread feed, clamped to the maximum.
+fn unread_limit(params: &HashMap<String, String>) -> i64 {
+    params.get("limit")
+        .and_then(|limit| limit.parse::<i64>().ok())
+        .unwrap_or(DEFAULT_UNREAD_ITEMS)
+        .clamp(1, MAX_UNREAD_ITEMS)
+}
+
+async fn handle_unread_output(
+    params: HashMap<String, String>,
+    headers: FeedHeaders,
+    key: Option<ApiKey>,
+    options: &PublishOptions,
+    mut conn: PooledPgConnection,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    if !is_authorized(&params, key.as_ref()) {
+        return Ok(warp::reply::with_status(
+            warp::reply::with_header(String::new(), "content-type", "text/plain"),
+            StatusCode::UNAUTHORIZED,
+        ).into_response());
+    }
+
+    let format = Format::negotiate(headers.accept.as_deref());
+    let limit = unread_limit(&params);
+    // Keep the limit so subscribers to the self url get the same feed
+    let self_url = match params.get("limit") {
+        Some(_) => options.self_url(&format!("/feeds/unread?limit={}", limit)),
+        None => options.self_url("/feeds/unread"),
+    };
+    let feed = publish::render_unread(limit, format, self_url.as_deref(), options, &mut conn)
+        .map_err(warp::reject::custom)?;
+    Ok(feed_reply(
+        feed, format, headers.if_none_match.as_deref(), headers.if_modified_since.as_deref(),
+    ))
+}
+
+async fn handle_feed_refresh(
+    id: i32,
+    params: HashMap<String, String>,
+    fetcher: Fetcher,
+    options: IngestOptions,
+    key: Option<ApiKey>,
+    mut conn: PooledPgConnection,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    if !is_authorized(&params, key.as_ref()) {
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&serde_json::json!({})),
+            StatusCode::UNAUTHORIZED,
+        ));
+    }
+
+    let feed = data::load_feed(id, &mut conn)
+        .map_err(fill_err!("Error loading feed"))
+        .map_err(warp::reject::custom)?
+        .ok_or_else(warp::reject::not_found)?;
+    let new_items = fetch::fetch_feed_items(&fetcher, &feed, &options, &mut conn).await
+        .map_err(warp::reject::custom)?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&serde_json::json!({ "new_items": new_items })),
+        StatusCode::OK,
+    ))
+}
+
+fn routes(
+    key: Option<ApiKey>,
+    fetcher: Fetcher,
+    options: IngestOptions,
+    response_options: ResponseOptions,
+    publish_options: PublishOptions,
     pool: PgConnectionPool,
-) {
-    let key = creds.map(|(user, pass)| ApiKey::new(&user, &pass));
+) -> impl Filter<Extract=(impl warp::Reply,), Error=warp::Rejection> + Clone {
+    let api_key = key.clone();
+    let refresh_key = key.clone();
+    let unread_key = key.clone();
+    let unread_options = publish_options.clone();
     let api = warp::post()
         .and(warp::query::<Vec<(String, String)>>())
         .and(warp::body::form::<HashMap<String, String>>())
         .and_then(parse_request)
         .and(connect_db(pool.clone()))
         .and_then(move |request, conn| {
-            handle_request(request, key.clone(), conn)
+            handle_request(request, api_key.clone(), response_options, conn)
+        });
+
+    let unread_output = warp::get()
+        .and(warp::path!("feeds" / "unread"))
+        .and(warp::query::<HashMap<String, String>>())
+        .and(feed_headers())
+        .and(connect_db(pool.clone()))
+        .and_then(move |params, headers, conn| {
+            let key = unread_key.clone();
+            let options = unread_options.clone();
+            async move { handle_unread_output(params, headers, key, &options, conn).await }
+        });
+
+    let feed_output = warp::get()
+        .and(warp::path!("feeds" / i32))
+        .and(warp::query::<HashMap<String, String>>())
+        .and(feed_headers())
+        .and(connect_db(pool.clone()))
+        .and_then(move |id, params, headers, conn| {
+            let key = key.clone();
+            let options = publish_options.clone();
+            async move { handle_feed_output(id, params, headers, key, &options, conn).await }
+        });
+
+    let refresh_fetcher = fetcher.clone();
+    let refresh_options = options.clone();
+    let feed_refresh = warp::post()
+        .and(warp::path!("feeds" / i32 / "refresh"))
+        .and(warp::query::<HashMap<String, String>>())
+        .and(connect_db(pool.clone()))
+        .and_then(move |id, params, conn| {
+            let fetcher = refresh_fetcher.clone();
+            let options = refresh_options.clone();
+            handle_feed_refresh(id, params, fetcher, options, refresh_key.clone(), conn)
         });
 
     let refresh = warp::get()
@@ -106,9 +310,269 @@ pub async fn serve(
         .and_then(accept_refresh)
         .untuple_one()
         .and(connect_db(pool.clone()))
-        .and_then(handle_refresh);
+        .and_then(move |conn| handle_refresh(fetcher.clone(), options.clone(), conn));
 
-    let route = api.or(refresh).with(warp::log("feeds"));
+    feed_refresh.or(api).or(unread_output).or(feed_output).or(refresh)
+}
+
+pub async fn serve(
+    port: u16,
+    creds: Option<(String, String)>,
+    fetcher: Fetcher,
+    options: IngestOptions,
+    response_options: ResponseOptions,
+    publish_options: PublishOptions,
+    pool: PgConnectionPool,
+) {
+    let key = creds.map(|(user, pass)| ApiKey::new(&user, &pass));
+    let route = routes(key, fetcher, options, response_options, publish_options, pool)
+        .with(warp::log("feeds"));
 
     warp::serve(route).run(([0, 0, 0, 0], port)).await;
 }
+
+#[cfg(test)]
+mod tests {
+    use atom_syndication as atom;
+    use chrono::{TimeZone, Utc};
+    use fever_api::Key as ApiKey;
+    use warp::Filter;
+    use warp::http::StatusCode;
+
+    use crate::data;
+    use crate::fetch::{Fetcher, IngestOptions};
+    use crate::handling::ResponseOptions;
+    use crate::publish::{Format, PublishOptions, RenderedFeed};
+    use crate::testing;
+    use super::{feed_reply, is_not_modified, routes, unread_limit};
+
+    fn rendered_feed() -> RenderedFeed {
+        RenderedFeed {
+            body: "<feed/>".to_owned(),
+            updated: Utc.with_ymd_and_hms(2019, 4, 1, 7, 30, 0).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_feed_etag() {
+        let route = warp::header::optional::<String>("if-none-match")
+            .map(|if_none_match: Option<String>| {
+                feed_reply(rendered_feed(), Format::Atom, if_none_match.as_deref(), None)
+            });
+
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async {
+            let response = warp::test::request().reply(&route).await;
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(response.body(), "<feed/>");
+            assert_eq!(response.headers()["last-modified"], "Mon, 01 Apr 2019 07:30:00 GMT");
+            assert_eq!(response.headers()["vary"], "accept");
+            let etag = response.headers()["etag"].clone();
+
+            let response = warp::test::request()
+                .header("if-none-match", etag)
+                .reply(&route)
+                .await;
+            assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+            assert!(response.body().is_empty());
+            assert_eq!(response.headers()["vary"], "accept");
+        });
+    }
+
+    #[test]
+    fn test_is_not_modified() {
+        let feed = rendered_feed();
+        let etag = feed.etag();
+        assert!(!is_not_modified(&feed, None, None));
+        assert!(is_not_modified(&feed, Some(&format!("\"other\", W/{}", etag)), None));
+        assert!(is_not_modified(&feed, Some("*"), None));
+        assert!(!is_not_modified(&feed, Some("\"other\""), None));
+
+        assert!(is_not_modified(&feed, None, Some("Mon, 01 Apr 2019 07:30:00 GMT")));
+        assert!(!is_not_modified(&feed, None, Some("Mon, 01 Apr 2019 07:29:59 GMT")));
+        // If-None-Match takes precedence
+        let since = Some("Mon, 01 Apr 2019 07:30:00 GMT");
+        assert!(!is_not_modified(&feed, Some("\"other\""), since));
+    }
+
+    #[test]
+    fn test_unread_limit() {
+        let params = |limit: &str| vec![("limit".to_owned(), limit.to_owned())].into_iter().collect();
+        assert_eq!(unread_limit(&Default::default()), 50);
+        assert_eq!(unread_limit(&params("10")), 10);
+        assert_eq!(unread_limit(&params("100000")), 500);
+        assert_eq!(unread_limit(&params("0")), 1);
+        assert_eq!(unread_limit(&params("all")), 50);
+    }
+
+    #[test]
+    fn test_feed_refresh_route() {
+        let Some(pool) = testing::pool() else { return };
+        let rss = r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0">
+  <channel>
+    <title>TechCrunch</title>
+    <link>http://techcrunch.com</link>
+    <description>The latest technology news and information on startups</description>
+    <item>
+      <title>Elon Musk leaves Ford</title>
+      <link>http://techcrunch.com/musk-leaves</link>
+      <pubDate>Tue, 02 Apr 2019 07:30:00 GMT</pubDate>
+    </item>
+    <item>
+      <title>Ford hires Elon Musk as CEO</title>
+      <link>http://techcrunch.com/ford-musk</link>
+      <pubDate>Mon, 01 Apr 2019 07:30:00 GMT</pubDate>
+    </item>
+  </channel>
+</rss>"#;
+
+        let key = ApiKey::new("user", "pass");
+        let api_key = key.to_string();
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async {
+            let feed_route = warp::any().map(move || {
+                warp::reply::with_header(rss, "content-type", "application/rss+xml")
+            });
+            let (addr, server) = warp::serve(feed_route)
+                .bind_ephemeral(([127, 0, 0, 1], 0));
+            tokio::spawn(server);
+
+            let feed = {
+                let mut conn = pool.get().unwrap();
+                testing::insert_feed(&format!("http://{}/feed", addr), "TechCrunch", &mut conn)
+            };
+            let route = routes(
+                Some(key),
+                Fetcher::new(1024 * 1024),
+                IngestOptions::default(),
+                ResponseOptions::default(),
+                PublishOptions::default(),
+                pool.clone(),
+            );
+            let path = format!("/feeds/{}/refresh", feed.id);
+
+            let response = warp::test::request()
+                .method("POST")
+                .path(&format!("{}?api_key=wrong", path))
+                .reply(&route)
+                .await;
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+            let response = warp::test::request()
+                .method("POST")
+                .path(&format!("{}?api_key={}", path, api_key))
+                .reply(&route)
+                .await;
+            assert_eq!(response.status(), StatusCode::OK);
+            let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+            assert_eq!(body, serde_json::json!({ "new_items": 2 }));
+
+            let mut conn = pool.get().unwrap();
+            let titles: Vec<_> = data::load_feed_items(&feed, &mut conn).unwrap()
+                .into_iter()
+                .map(|item| item.title)
+                .collect();
+            assert_eq!(titles.len(), 2);
+            assert!(titles.contains(&"Elon Musk leaves Ford".to_owned()));
+            assert!(titles.contains(&"Ford hires Elon Musk as CEO".to_owned()));
+        });
+    }
+
+    #[test]
+    fn test_unread_route() {
+        let Some(pool) = testing::pool() else { return };
+        {
+            let mut conn = pool.get().unwrap();
+            let techcrunch = testing::insert_feed("http://techcrunch.com/feed/", "TechCrunch", &mut conn);
+            let verge = testing::insert_feed("http://www.theverge.com/rss/index.xml", "The Verge", &mut conn);
+            testing::insert_item(&techcrunch, "Ford hires Elon Musk", 1, &mut conn);
+            testing::insert_item(&verge, "Tesla stock falls", 2, &mut conn);
+        }
+
+        let key = ApiKey::new("user", "pass");
+        let api_key = key.to_string();
+        let route = routes(
+            Some(key),
+            Fetcher::new(1024),
+            IngestOptions::default(),
+            ResponseOptions::default(),
+            PublishOptions {
+                base_url: Some("https://feeds.example.com".to_owned()),
+                ..PublishOptions::default()
+            },
+            pool,
+        );
+
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async {
+            let response = warp::test::request().path("/feeds/unread").reply(&route).await;
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+            let response = warp::test::request()
+                .path(&format!("/feeds/unread?api_key={}&limit=10", api_key))
+                .reply(&route)
+                .await;
+            assert_eq!(response.status(), StatusCode::OK);
+            let feed: atom::Feed = std::str::from_utf8(response.body()).unwrap().parse().unwrap();
+            let sources: Vec<_> = feed.entries().iter()
+                .map(|entry| (entry.title().as_str(), entry.source().unwrap().title().as_str()))
+                .collect();
+            assert_eq!(sources, [("Tesla stock falls", "The Verge"), ("Ford hires Elon Musk", "TechCrunch")]);
+            // The self link keeps the limit but not the api key
+            let self_link = feed.links().iter().find(|link| link.rel() == "self").unwrap();
+            assert_eq!(self_link.href(), "https://feeds.example.com/feeds/unread?limit=10");
+        });
+    }
+
+    #[test]
+    fn test_feed_route_formats() {
+        let Some(pool) = testing::pool() else { return };
+        let feed_id = {
+            let mut conn = pool.get().unwrap();
+            let techcrunch = testing::insert_feed("http://techcrunch.com/feed/", "TechCrunch", &mut conn);
+            testing::insert_item(&techcrunch, "Ford hires Elon Musk", 1, &mut conn);
+            techcrunch.id
+        };
+        let route = routes(
+            None,
+            Fetcher::new(1024),
+            IngestOptions::default(),
+            ResponseOptions::default(),
+            PublishOptions::default(),
+            pool,
+        );
+        let path = format!("/feeds/{}", feed_id);
+
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async {
+            let response = warp::test::request()
+                .path(&path)
+                .header("accept", "application/atom+xml")
+                .reply(&route)
+                .await;
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(response.headers()["content-type"], "application/atom+xml");
+            let feed: atom::Feed = std::str::from_utf8(response.body()).unwrap().parse().unwrap();
+            assert_eq!(feed.title().as_str(), "TechCrunch");
+            assert_eq!(feed.entries()[0].title().as_str(), "Ford hires Elon Musk");
+            let atom_etag = response.headers()["etag"].clone();
+
+            let response = warp::test::request()
+                .path(&path)
+                .header("accept", "application/feed+json")
+                .reply(&route)
+                .await;
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(response.headers()["content-type"], "application/feed+json");
+            let feed: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+            assert_eq!(feed["title"], "TechCrunch");
+            assert_eq!(feed["items"][0]["title"], "Ford hires Elon Musk");
+            // Each format is its own representation for caching
+            assert_ne!(response.headers()["etag"], atom_etag);
+
+            let response = warp::test::request().path(&path).reply(&route).await;
+            assert_eq!(response.headers()["content-type"], "application/atom+xml");
+        });
+    }
+}
@@ -0,0 +1,201 @@
+//! Helpers shared by the tests of different modules.
+
+use std::env;
+use std::fs;
+use std::sync::Once;
+
+use chrono::{NaiveDate, NaiveDateTime};
+use diesel::connection::SimpleConnection;
+use diesel::prelude::*;
+use diesel::pg::PgConnection;
+use diesel::r2d2;
+
+use crate::config::{PgConnectionManager, PgConnectionPool};
+use crate::models::feed::{Feed, NewFeed};
+use crate::models::item::{Item, NewItem};
+
+static MIGRATE: Once = Once::new();
+
+/// The url of a database that tests are free to reset, or None to skip
+/// the tests that need one.
+pub fn database_url() -> Option<String> {
+    let database_url = env::var("TEST_DATABASE_URL").ok();
+    if database_url.is_none() {
+        eprintln!("TEST_DATABASE_URL isn't set, skipping database test");
+    }
+    database_url
+}
+
+/// Recreates a schema of the test database by running every migration in it,
+/// leaving the connection using that schema.
+fn migrate_schema(conn: &mut PgConnection, schema: &str) {
+    let mut migrations: Vec<_> = fs::read_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/migrations"))
+        .expect("Error reading migrations")
+        .map(|entry| entry.expect("Error reading migration").path())
+        .collect();
+    migrations.sort();
+
+    let reset = format!(
+        "DROP SCHEMA IF EXISTS {0} CASCADE; CREATE SCHEMA {0}; SET search_path TO {0};",
+        schema,
+    );
+    conn.batch_execute(&reset)
+        .expect("Error resetting test database");
+    for migration in migrations {
+        let sql = fs::read_to_string(migration.join("up.sql"))
+            .expect("Error reading migration");
+        conn.batch_execute(&sql)
+            .unwrap_or_else(|err| panic!("Error running {}: {}", migration.display(), err));
+    }
+}
+
+/// Recreates the test database's public schema.
+fn migrate(database_url: &str) {
+    let mut conn = PgConnection::establish(database_url)
+        .expect("Error connecting to test database");
+    migrate_schema(&mut conn, "public");
+}
+
+/// Migrates the test database once per run and returns its url,
+/// or None if there's no test database.
+pub fn migrated_database_url() -> Option<String> {
+    let database_url = database_url()?;
+    MIGRATE.call_once(|| migrate(&database_url));
+    Some(database_url)
+}
+
+/// Connects to the test database in a transaction that's never committed,
+/// so tests don't see each other's changes.
+pub fn connection() -> Option<PgConnection> {
+    let database_url = migrated_database_url()?;
+    let mut conn = PgConnection::establish(&database_url)
+        .expect("Error connecting to test database");
+    conn.begin_test_transaction()
+        .expect("Error starting test transaction");
+    Some(conn)
+}
+
+/// Connects to a freshly migrated schema of the test database outside of
+/// a transaction, for testing statements that can't run in one.
+/// Each test needs its own schema, since its changes are committed.
+pub fn committed_connection(schema: &str) -> Option<PgConnection> {
+    let database_url = database_url()?;
+    let mut conn = PgConnection::establish(&database_url)
+        .expect("Error connecting to test database");
+    migrate_schema(&mut conn, schema);
+    Some(conn)
+}
+
+/// Starts each pooled connection in a transaction that's never committed.
+#[derive(Debug)]
+struct TestTransaction;
+
+impl r2d2::CustomizeConnection<PgConnection, r2d2::Error> for TestTransaction {
+    fn on_acquire(&self, conn: &mut PgConnection) -> Result<(), r2d2::Error> {
+        conn.begin_test_transaction().map_err(r2d2::Error::QueryError)
+    }
+}
+
+/// A pool of a single connection to the test database, in a transaction
+/// that's never committed, for testing code that takes a pool.
+pub fn pool() -> Option<PgConnectionPool> {
+    let database_url = migrated_database_url()?;
+    let pool = PgConnectionPool::builder()
+        .max_size(1)
+        .connection_customizer(Box::new(TestTransaction))
+        .build(PgConnectionManager::new(database_url))
+        .expect("Error creating test pool");
+    Some(pool)
+}
+
+/// The date of the nth day of April 2019, when most test items are published.
+pub fn april(day: u32) -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(2019, 4, day).unwrap().and_hms_opt(7, 30, 0).unwrap()
+}
+
+/// A feed that was never stored, with nothing but an id and url.
+pub fn feed(id: i32, url: &str) -> Feed {
+    Feed {
+        id,
+        url: url.to_owned(),
+        title: String::new(),
+        group_id: None,
+        site_url: None,
+        is_disabled: false,
+        subtitle: None,
+        has_custom_title: false,
+        auth_username: None,
+        auth_password: None,
+        auth_token: None,
+        next_fetch: None,
+        read_before: None,
+        last_warnings: None,
+        mark_read_on_fetch: false,
+        fingerprint: None,
+        backfill_url: None,
+    }
+}
+
+/// An unread item that was never stored, published on the given day of
+/// April 2019, with its url under the feed's site.
+pub fn item(id: i32, feed: &Feed, title: &str, day: u32) -> Item {
+    Item {
+        id,
+        url: Some(format!("{}/{}", feed.site_url.as_deref().unwrap_or(""), id)),
+        title: title.to_owned(),
+        content: String::new(),
+        published: april(day),
+        feed_id: feed.id,
+        is_read: false,
+        is_saved: false,
+        author: None,
+        fetched: april(day),
+        guid: None,
+        summary: None,
+        updated: None,
+    }
+}
+
+/// Stores a feed with the given url and title.
+pub fn insert_feed(url: &str, title: &str, conn: &mut PgConnection) -> Feed {
+    use crate::schema::feed;
+
+    let new_feed = NewFeed {
+        url,
+        title,
+        site_url: None,
+        group_id: None,
+        subtitle: None,
+        has_custom_title: false,
+        auth_username: None,
+        auth_password: None,
+        auth_token: None,
+    };
+    diesel::insert_into(feed::table)
+        .values(&new_feed)
+        .get_result(conn)
+        .expect("Error inserting test feed")
+}
+
+/// Stores an unread item of a feed, published on the given day of April 2019.
+pub fn insert_item(feed: &Feed, title: &str, day: u32, conn: &mut PgConnection) -> Item {
+    use crate::schema::item;
+
+    let url = format!("{}#{}", feed.url, title);
+    let new_item = NewItem {
+        url: Some(&url),
+        title,
+        content: "",
+        published: Some(april(day)),
+        updated: None,
+        feed_id: feed.id,
+        author: None,
+        guid: None,
+        summary: None,
+        is_read: false,
+    };
+    diesel::insert_into(item::table)
+        .values(&new_item)
+        .get_result(conn)
+        .expect("Error inserting test item")
+}
@@ -0,0 +1,93 @@
+//! Tests that run the feeds binary against the test database.
+
+use std::env;
+use std::fs;
+use std::process::Command;
+
+use diesel::connection::SimpleConnection;
+use diesel::prelude::*;
+use diesel::pg::PgConnection;
+use tokio::runtime::Runtime;
+use warp::Filter;
+
+static RSS_STR: &'static str = r#"
+<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0">
+  <channel>
+    <title>TechCrunch</title>
+    <link>http://techcrunch.com</link>
+    <item>
+      <title>Ford hires Elon Musk as CEO</title>
+      <link>http://techcrunch.com/2015/03/31/ford-hires-elon-musk-as-ceo/</link>
+    </item>
+  </channel>
+</rss>
+"#;
+
+/// Recreates the test database's schema by running every migration,
+/// or returns None if there's no test database.
+fn migrated_database_url() -> Option<String> {
+    let database_url = match env::var("TEST_DATABASE_URL") {
+        Ok(database_url) => database_url,
+        Err(_) => {
+            eprintln!("TEST_DATABASE_URL isn't set, skipping database test");
+            return None;
+        }
+    };
+
+    let mut conn = PgConnection::establish(&database_url)
+        .expect("Error connecting to test database");
+    let mut migrations: Vec<_> = fs::read_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/migrations"))
+        .expect("Error reading migrations")
+        .map(|entry| entry.expect("Error reading migration").path())
+        .collect();
+    migrations.sort();
+
+    conn.batch_execute("DROP SCHEMA public CASCADE; CREATE SCHEMA public;")
+        .expect("Error resetting test database");
+    for migration in migrations {
+        let sql = fs::read_to_string(migration.join("up.sql"))
+            .expect("Error reading migration");
+        conn.batch_execute(&sql)
+            .unwrap_or_else(|err| panic!("Error running {}: {}", migration.display(), err));
+    }
+    Some(database_url)
+}
+
+#[test]
+fn test_json_output() {
+    let database_url = match migrated_database_url() {
+        Some(database_url) => database_url,
+        None => return,
+    };
+
+    let rt = Runtime::new().unwrap();
+    let route = warp::any()
+        .map(|| warp::reply::with_header(RSS_STR, "content-type", "application/rss+xml"));
+    let (addr, server) = rt.block_on(async {
+        warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0))
+    });
+    rt.spawn(server);
+    let url = format!("http://{}/", addr);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_feeds"))
+        .args(["subscribe", "--json", &url])
+        .env("DATABASE_URL", &database_url)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    // Progress goes to stderr so stdout is only the result
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Found 1 items"));
+    let subscription: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(subscription["title"], "TechCrunch");
+    assert_eq!(subscription["items"], 1);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_feeds"))
+        .args(["fetch", "--json"])
+        .env("DATABASE_URL", &database_url)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let fetched: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(fetched["new_items"], 0);
+}